@@ -0,0 +1,83 @@
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+use too_many_lists::{arena, third};
+
+// cargo bench --bench arena
+// a plain timing loop, no external harness: builds, walks and frees a list of N elements,
+// once with Rc nodes (third::List) and once in an arena
+const N: usize = 1_000_000;
+const ROUNDS: u32 = 10;
+
+fn time<F: FnMut()>(name: &str, mut f: F) {
+    // one warm-up round, then the average
+    f();
+    let mut total = Duration::ZERO;
+    for _ in 0..ROUNDS {
+        let start = Instant::now();
+        f();
+        total += start.elapsed();
+    }
+    println!("{:<36} {:>10.2?}", name, total / ROUNDS);
+}
+
+fn main() {
+    time("rc: prepend + drop", || {
+        let mut list = third::List::new();
+        for i in 0..N {
+            list = list.prepend(i);
+        }
+        black_box(&list);
+    });
+
+    time("arena: prepend + free", || {
+        let arena = arena::Arena::new();
+        let mut list = arena.list();
+        for i in 0..N {
+            list = list.prepend(i);
+        }
+        black_box(list);
+    });
+
+    time("rc: prepend + walk tails + drop", || {
+        let mut list = third::List::new();
+        for i in 0..N {
+            list = list.prepend(i);
+        }
+        let mut sum = 0;
+        let mut cur = list.clone();
+        while let Some(val) = cur.head() {
+            sum += val;
+            cur = cur.tail();
+        }
+        black_box(sum);
+    });
+
+    time("arena: prepend + walk tails + free", || {
+        let arena = arena::Arena::new();
+        let mut list = arena.list();
+        for i in 0..N {
+            list = list.prepend(i);
+        }
+        let mut sum = 0;
+        let mut cur = list;
+        while let Some(val) = cur.head() {
+            sum += val;
+            cur = cur.tail();
+        }
+        black_box(sum);
+    });
+
+    // many versions sharing one long tail, as a backtracking parser would keep them
+    time("rc: 1000 branches off one tail", || {
+        let base = (0..N).fold(third::List::new(), |mut list, i| list.prepend(i));
+        let branches: Vec<_> = (0..1000).map(|i| base.clone().tail().prepend(i)).collect();
+        black_box(branches);
+    });
+
+    time("arena: 1000 branches off one tail", || {
+        let arena = arena::Arena::new();
+        let base = (0..N).fold(arena.list(), |list, i| list.prepend(i));
+        let branches: Vec<_> = (0..1000).map(|i| base.clone().tail().prepend(i)).collect();
+        black_box(branches);
+    });
+}
@@ -0,0 +1,237 @@
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::hint::black_box;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+use too_many_lists::fourth;
+
+// cargo bench --bench fourth_layout
+// fourth::List against a copy of the layout it started from, Rc<RefCell<Node<T>>> with the element and both
+// links (prev a strong Rc, cut on every pop) inside the RefCell: bytes allocated per node, and push/pop
+// throughput at both ends. the node is not smaller now: a node of a usize is 64 bytes where it was 48,
+// see the top of src/fourth.rs for what the two extra words are
+const N: usize = 1_000_000;
+const ROUNDS: u32 = 10;
+
+// counts the bytes handed out, to see what one node costs
+struct Counting;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+// the old layout, only as much of it as the benchmark needs
+mod before {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    type Link<T> = Option<Rc<RefCell<Node<T>>>>;
+
+    struct Node<T> {
+        val: T,
+        next: Link<T>,
+        prev: Link<T>,
+    }
+
+    pub struct List<T> {
+        head: Link<T>,
+        tail: Link<T>,
+    }
+
+    impl<T> Node<T> {
+        fn new(val: T) -> Rc<RefCell<Self>> {
+            Rc::new(RefCell::new(Node {
+                val,
+                prev: None,
+                next: None,
+            }))
+        }
+    }
+
+    impl<T> List<T> {
+        pub fn new() -> Self {
+            List {
+                head: None,
+                tail: None,
+            }
+        }
+
+        pub fn push_front(&mut self, val: T) {
+            let new_head = Node::new(val);
+            match self.head.take() {
+                None => {
+                    self.tail = Some(new_head.clone());
+                    self.head = Some(new_head);
+                }
+                Some(old_head) => {
+                    old_head.borrow_mut().prev = Some(new_head.clone());
+                    new_head.borrow_mut().next = Some(old_head);
+                    self.head = Some(new_head);
+                }
+            }
+        }
+
+        pub fn push_back(&mut self, val: T) {
+            let new_tail = Node::new(val);
+            match self.tail.take() {
+                None => {
+                    self.tail = Some(new_tail.clone());
+                    self.head = Some(new_tail);
+                }
+                Some(old_tail) => {
+                    old_tail.borrow_mut().next = Some(new_tail.clone());
+                    new_tail.borrow_mut().prev = Some(old_tail);
+                    self.tail = Some(new_tail);
+                }
+            }
+        }
+
+        pub fn pop_front(&mut self) -> Option<T> {
+            self.head.take().map(|old_head| {
+                match old_head.borrow_mut().next.take() {
+                    Some(new_head) => {
+                        new_head.borrow_mut().prev.take();
+                        self.head = Some(new_head);
+                    }
+                    None => {
+                        self.tail.take();
+                    }
+                }
+                Rc::try_unwrap(old_head).ok().unwrap().into_inner().val
+            })
+        }
+
+        pub fn pop_back(&mut self) -> Option<T> {
+            self.tail.take().map(|old_tail| {
+                match old_tail.borrow_mut().prev.take() {
+                    Some(new_tail) => {
+                        new_tail.borrow_mut().next.take();
+                        self.tail = Some(new_tail);
+                    }
+                    None => {
+                        self.head.take();
+                    }
+                }
+                Rc::try_unwrap(old_tail).ok().unwrap().into_inner().val
+            })
+        }
+    }
+
+    impl<T> Drop for List<T> {
+        fn drop(&mut self) {
+            while self.pop_front().is_some() {}
+        }
+    }
+}
+
+fn time<F: FnMut()>(name: &str, mut f: F) {
+    // one warm-up round, then the average
+    f();
+    let mut total = Duration::ZERO;
+    for _ in 0..ROUNDS {
+        let start = Instant::now();
+        f();
+        total += start.elapsed();
+    }
+    println!("{:<36} {:>10.2?}", name, total / ROUNDS);
+}
+
+// bytes allocated by building a list of N elements, per element
+fn per_node<L>(build: impl FnOnce() -> L) -> usize {
+    let start = ALLOCATED.load(Ordering::Relaxed);
+    let list = build();
+    let bytes = ALLOCATED.load(Ordering::Relaxed) - start;
+    drop(black_box(list));
+    bytes / N
+}
+
+fn main() {
+    let old = per_node(|| {
+        let mut list = before::List::new();
+        for i in 0..N {
+            list.push_back(i);
+        }
+        list
+    });
+    let new = per_node(|| {
+        let mut list = fourth::List::new();
+        for i in 0..N {
+            list.push_back(i);
+        }
+        list
+    });
+    println!("{:<36} {:>7} bytes", "before: per node (usize)", old);
+    println!("{:<36} {:>7} bytes", "after: per node (usize)", new);
+
+    time("before: push_back + pop_front", || {
+        let mut list = before::List::new();
+        for i in 0..N {
+            list.push_back(i);
+        }
+        while let Some(val) = list.pop_front() {
+            black_box(val);
+        }
+    });
+
+    time("after: push_back + pop_front", || {
+        let mut list = fourth::List::new();
+        for i in 0..N {
+            list.push_back(i);
+        }
+        while let Some(val) = list.pop_front() {
+            black_box(val);
+        }
+    });
+
+    time("before: push_front + pop_back", || {
+        let mut list = before::List::new();
+        for i in 0..N {
+            list.push_front(i);
+        }
+        while let Some(val) = list.pop_back() {
+            black_box(val);
+        }
+    });
+
+    time("after: push_front + pop_back", || {
+        let mut list = fourth::List::new();
+        for i in 0..N {
+            list.push_front(i);
+        }
+        while let Some(val) = list.pop_back() {
+            black_box(val);
+        }
+    });
+
+    // a queue that stays short, where every node is fresh
+    time("before: 1000-element queue churn", || {
+        let mut list = before::List::new();
+        for i in 0..N {
+            list.push_back(i);
+            if i >= 1000 {
+                black_box(list.pop_front());
+            }
+        }
+    });
+
+    time("after: 1000-element queue churn", || {
+        let mut list = fourth::List::new();
+        for i in 0..N {
+            list.push_back(i);
+            if i >= 1000 {
+                black_box(list.pop_front());
+            }
+        }
+    });
+}
@@ -0,0 +1,240 @@
+use crate::third;
+use std::cell::RefCell;
+use std::ptr::NonNull;
+
+// third::List without reference counting: nodes live in an Arena and lists borrow them
+//
+// prepend() bumps one slot in the arena's current chunk, tail() copies a pointer, and a list is just
+// (arena, head), so it is Copy. nothing is freed while the arena lives: dropping the arena drops every node
+// in one go, chunk by chunk, which is exactly what a short-lived pass like a parser wants.
+// the borrow checker makes sure no List<'a, T> outlives the arena its nodes are in
+pub struct Arena<T> {
+    // a chunk is never pushed past its capacity, so its buffer never moves and references into it stay valid
+    // for as long as the arena lives. a full chunk is left alone and a twice as large one is started
+    chunks: RefCell<Vec<Vec<Node<T>>>>,
+}
+
+struct Node<T> {
+    val: T,
+    // a raw pointer instead of a reference, so Node does not need the arena's lifetime.
+    // it always points into the same arena as the node itself
+    next: Option<NonNull<Node<T>>>,
+}
+
+pub struct List<'a, T> {
+    arena: &'a Arena<T>,
+    head: Option<&'a Node<T>>,
+}
+
+const FIRST_CHUNK: usize = 64;
+
+impl<T> Arena<T> {
+    pub fn new() -> Self {
+        Arena::with_capacity(FIRST_CHUNK)
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        Arena {
+            chunks: RefCell::new(vec![Vec::with_capacity(capacity.max(1))]),
+        }
+    }
+
+    // the empty list, ready to prepend onto
+    pub fn list(&self) -> List<'_, T> {
+        List {
+            arena: self,
+            head: None,
+        }
+    }
+
+    // number of nodes allocated so far
+    pub fn len(&self) -> usize {
+        self.chunks.borrow().iter().map(Vec::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // frees every node but keeps the largest chunk for the next pass.
+    // &mut self means no list borrows the arena anymore
+    pub fn reset(&mut self) {
+        let chunks = self.chunks.get_mut();
+        let mut largest = chunks.pop().unwrap();
+        chunks.clear();
+        largest.clear();
+        chunks.push(largest);
+    }
+
+    fn alloc(&self, node: Node<T>) -> &Node<T> {
+        let mut chunks = self.chunks.borrow_mut();
+        let chunk = chunks.last_mut().unwrap();
+        if chunk.len() == chunk.capacity() {
+            let capacity = chunk.capacity() * 2;
+            chunks.push(Vec::with_capacity(capacity));
+        }
+        let chunk = chunks.last_mut().unwrap();
+        chunk.push(node);
+        let node = chunk.last().unwrap() as *const Node<T>;
+        // the node sits in a buffer that is neither moved nor freed before the arena is dropped or reset,
+        // and both of those need the arena by value or &mut, which cannot happen while this &self borrow lives
+        unsafe { &*node }
+    }
+}
+
+impl<T> Default for Arena<T> {
+    fn default() -> Self {
+        Arena::new()
+    }
+}
+
+impl<'a, T> List<'a, T> {
+    pub fn prepend(&self, val: T) -> List<'a, T> {
+        let node = self.arena.alloc(Node {
+            val,
+            next: self.head.map(NonNull::from),
+        });
+        List {
+            arena: self.arena,
+            head: Some(node),
+        }
+    }
+
+    pub fn tail(&self) -> List<'a, T> {
+        List {
+            arena: self.arena,
+            head: self.head.and_then(next),
+        }
+    }
+
+    pub fn head(&self) -> Option<&'a T> {
+        self.head.map(|node| &node.val)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.head.is_none()
+    }
+
+    // same node (or both empty), like third::List::ptr_eq
+    pub fn ptr_eq(&self, other: &List<'a, T>) -> bool {
+        match (self.head, other.head) {
+            (Some(a), Some(b)) => std::ptr::eq(a, b),
+            (None, None) => true,
+            _ => false,
+        }
+    }
+
+    pub fn iter(&self) -> Iter<'a, T> {
+        Iter { next: self.head }
+    }
+}
+
+// next nodes are always in the arena the list borrows for 'a
+fn next<T>(node: &Node<T>) -> Option<&Node<T>> {
+    node.next.map(|next| unsafe { &*next.as_ptr() })
+}
+
+// a list handle is two pointers, copying it copies neither nodes nor elements
+impl<T> Clone for List<'_, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for List<'_, T> {}
+
+// copies the elements into Rc nodes that no longer depend on the arena
+impl<T: Clone> From<List<'_, T>> for third::List<T> {
+    fn from(list: List<'_, T>) -> Self {
+        let vals: Vec<&T> = list.iter().collect();
+        vals.into_iter()
+            .rev()
+            .fold(third::List::new(), |mut list, val| list.prepend(val.clone()))
+    }
+}
+
+pub struct Iter<'a, T> {
+    next: Option<&'a Node<T>>,
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = self.next?;
+        self.next = next(node);
+        Some(&node.val)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Arena;
+    use crate::third;
+    use std::cell::Cell;
+
+    #[test]
+    fn basic_test() {
+        let arena = Arena::new();
+        let empty = arena.list();
+        assert_eq!(empty.head(), None);
+        assert!(empty.tail().is_empty());
+
+        let list = empty.prepend(1).prepend(2).prepend(3);
+        assert_eq!(list.head(), Some(&3));
+        let tail = list.tail();
+        assert_eq!(tail.head(), Some(&2));
+
+        // handles are Copy and versions share nodes just like third::List
+        let copy = list;
+        let other = tail.prepend(4);
+        assert!(copy.ptr_eq(&list));
+        assert!(other.tail().ptr_eq(&tail));
+        assert_eq!(list.iter().collect::<Vec<_>>(), vec![&3, &2, &1]);
+        assert_eq!(arena.len(), 4);
+    }
+
+    #[test]
+    fn grow_test() {
+        let arena = Arena::with_capacity(2);
+        let mut list = arena.list();
+        for i in 0..10_000 {
+            list = list.prepend(i);
+        }
+        // nodes from every chunk are still where they were
+        assert_eq!(list.iter().count(), 10_000);
+        assert_eq!(list.iter().last(), Some(&0));
+
+        let owned: third::List<i32> = list.into();
+        assert_eq!(owned.head(), Some(&9_999));
+        assert_eq!(owned.iter().count(), 10_000);
+    }
+
+    #[test]
+    fn drop_test() {
+        struct Counted<'c>(&'c Cell<usize>);
+        impl Drop for Counted<'_> {
+            fn drop(&mut self) {
+                self.0.set(self.0.get() + 1);
+            }
+        }
+
+        let drops = Cell::new(0);
+        let mut arena = Arena::with_capacity(4);
+        {
+            let list = arena.list().prepend(Counted(&drops));
+            let _ = list.prepend(Counted(&drops)).prepend(Counted(&drops));
+        }
+        // dropping lists frees nothing, resetting the arena frees everything
+        assert_eq!(drops.get(), 0);
+        arena.reset();
+        assert_eq!(drops.get(), 3);
+        assert!(arena.is_empty());
+
+        let list = arena.list();
+        for _ in 0..100 {
+            list.prepend(Counted(&drops));
+        }
+        drop(arena);
+        assert_eq!(drops.get(), 103);
+    }
+}
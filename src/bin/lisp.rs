@@ -0,0 +1,38 @@
+use std::io::{self, BufRead, Write};
+use too_many_lists::lisp::{Interpreter, LispError};
+
+// a line-based repl, input is buffered until it forms complete expressions
+fn main() {
+    let mut interp = Interpreter::new();
+    let mut input = String::new();
+    let stdin = io::stdin();
+
+    prompt("> ");
+    for line in stdin.lock().lines() {
+        let line = match line {
+            Ok(line) => line,
+            Err(err) => {
+                eprintln!("error: {}", err);
+                break;
+            }
+        };
+        input.push_str(&line);
+        input.push('\n');
+
+        match interp.eval_str(&input) {
+            Err(LispError::Incomplete) => {
+                prompt(". ");
+                continue;
+            }
+            Ok(val) => println!("{}", val),
+            Err(err) => eprintln!("error: {}", err),
+        }
+        input.clear();
+        prompt("> ");
+    }
+}
+
+fn prompt(s: &str) {
+    print!("{}", s);
+    let _ = io::stdout().flush();
+}
@@ -0,0 +1,286 @@
+use crate::third;
+use crate::third::{List, Node};
+use std::borrow::Borrow;
+use std::cell::{OnceCell, RefCell};
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::ops::Deref;
+use std::rc::Rc;
+
+// a lexically scoped symbol table (an association list) on top of third::List
+//
+// binding prepends onto the current version, so the innermost binding of a key is the one closest to the head
+// and outer bindings of the same key are shadowed rather than overwritten.
+// entering a scope remembers the current bindings list (one Rc clone), and leaving it simply goes back to it:
+// nothing has to be removed, the bindings made inside the scope are dropped together with the last version using them
+pub struct Env<K, V> {
+    bindings: List<(K, V)>,
+    // the bindings as they were when each open scope was entered, innermost scope first,
+    // together with their index so that leaving the scope gets it back too
+    scopes: List<Scope<K, V>>,
+    // made on the first call to index() and shared from then on by every version with the same bindings
+    index: OnceCell<Rc<Index<K, V>>>,
+}
+
+type Scope<K, V> = (List<(K, V)>, OnceCell<Rc<Index<K, V>>>);
+
+impl<K, V> Env<K, V> {
+    pub fn new() -> Self {
+        Env {
+            bindings: List::new(),
+            scopes: List::new(),
+            index: OnceCell::new(),
+        }
+    }
+
+    pub fn bind(&self, key: K, val: V) -> Env<K, V> {
+        Env {
+            bindings: self.bindings.clone().prepend((key, val)),
+            scopes: self.scopes.clone(),
+            index: OnceCell::new(),
+        }
+    }
+
+    // the bindings do not change, so neither does the index
+    pub fn push_scope(&self) -> Env<K, V> {
+        Env {
+            bindings: self.bindings.clone(),
+            scopes: self
+                .scopes
+                .clone()
+                .prepend((self.bindings.clone(), self.index.clone())),
+            index: self.index.clone(),
+        }
+    }
+
+    // None if no scope is open
+    pub fn pop_scope(&self) -> Option<Env<K, V>> {
+        self.scopes.head().map(|(outer, index)| Env {
+            bindings: outer.clone(),
+            scopes: self.scopes.clone().tail(),
+            index: index.clone(),
+        })
+    }
+
+    pub fn depth(&self) -> usize {
+        self.scopes.iter().count()
+    }
+
+    // every binding, innermost first, including the shadowed ones
+    pub fn iter(&self) -> third::Iter<'_, (K, V)> {
+        self.bindings.iter()
+    }
+
+    pub fn lookup<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: PartialEq + ?Sized,
+    {
+        self.bindings
+            .iter()
+            .find(|(k, _)| k.borrow() == key)
+            .map(|(_, v)| v)
+    }
+
+    // the bindings of key hidden by the innermost one, from the nearest outward
+    pub fn shadowed_bindings<'a, Q>(&'a self, key: &'a Q) -> impl Iterator<Item = &'a V> + 'a
+    where
+        K: Borrow<Q>,
+        Q: PartialEq + ?Sized,
+    {
+        self.bindings
+            .iter()
+            .filter(move |(k, _)| k.borrow() == key)
+            .map(|(_, v)| v)
+            .skip(1)
+    }
+}
+
+impl<K: Hash + Eq + Clone, V> Env<K, V> {
+    // a memoizing index over this version of the environment, for scopes with many hot lookups.
+    // it is kept with the version: clones made after this call, scopes pushed from it and the version
+    // a pop_scope returns to all use the same index and whatever it has memoized so far
+    pub fn index(&self) -> &Index<K, V> {
+        self.index
+            .get_or_init(|| Rc::new(Index::new(&self.bindings)))
+    }
+}
+
+impl<K, V> Default for Env<K, V> {
+    fn default() -> Self {
+        Env::new()
+    }
+}
+
+impl<K, V> Clone for Env<K, V> {
+    fn clone(&self) -> Self {
+        Env {
+            bindings: self.bindings.clone(),
+            scopes: self.scopes.clone(),
+            index: self.index.clone(),
+        }
+    }
+}
+
+// the index covers one version of the bindings, and versions never change,
+// so whatever it has memoized stays valid no matter how many newer versions are bound or popped meanwhile.
+// lookups resume the scan where the previous one stopped, remembering the innermost binding of every key
+// passed on the way, so each binding is visited at most once over all lookups on the version.
+// it holds the nodes it has found (and the next one to scan) by Rc, and hands out Rcs of them as well
+pub struct Index<K, V> {
+    scan: RefCell<Scan<K, V>>,
+}
+
+struct Scan<K, V> {
+    rest: third::Link<(K, V)>,
+    seen: HashMap<K, Rc<Node<(K, V)>>>,
+}
+
+impl<K: Hash + Eq + Clone, V> Index<K, V> {
+    fn new(bindings: &List<(K, V)>) -> Self {
+        Index {
+            scan: RefCell::new(Scan {
+                rest: bindings.head.clone(),
+                seen: HashMap::new(),
+            }),
+        }
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> Option<Found<K, V>>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let mut scan = self.scan.borrow_mut();
+        let node = match scan.seen.get(key) {
+            Some(node) => node.clone(),
+            None => scan.resume(key)?,
+        };
+        Some(Found(node))
+    }
+}
+
+// the value of a binding an index found. it keeps the binding's node alive,
+// so it stays valid after the index and every version holding the binding are gone
+pub struct Found<K, V>(Rc<Node<(K, V)>>);
+
+impl<K, V> Deref for Found<K, V> {
+    type Target = V;
+    fn deref(&self) -> &V {
+        &self.0.val.1
+    }
+}
+
+impl<K: Hash + Eq + Clone, V> Scan<K, V> {
+    fn resume<Q>(&mut self, key: &Q) -> Option<Rc<Node<(K, V)>>>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        while let Some(node) = self.rest.take() {
+            self.rest = node.next.clone();
+            let k = &node.val.0;
+            // a key seen before is shadowed here, keep the inner binding
+            if self.seen.contains_key::<K>(k) {
+                continue;
+            }
+            self.seen.insert(k.clone(), node.clone());
+            if <K as Borrow<Q>>::borrow(k) == key {
+                return Some(node);
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Env;
+
+    #[test]
+    fn bind_test() {
+        let env = Env::new().bind("x", 1).bind("y", 2);
+        assert_eq!(env.lookup("x"), Some(&1));
+        assert_eq!(env.lookup("z"), None);
+
+        let inner = env.bind("x", 10);
+        assert_eq!(inner.lookup("x"), Some(&10));
+        assert_eq!(inner.shadowed_bindings("x").collect::<Vec<_>>(), vec![&1]);
+        assert_eq!(inner.shadowed_bindings("y").count(), 0);
+
+        // the outer version is untouched
+        assert_eq!(env.lookup("x"), Some(&1));
+    }
+
+    #[test]
+    fn scope_test() {
+        let global = Env::new().bind("x", 1);
+        assert!(global.pop_scope().is_none());
+
+        let local = global.push_scope().bind("x", 2).bind("y", 3);
+        let nested = local.push_scope().bind("y", 4);
+        assert_eq!(nested.depth(), 2);
+        assert_eq!(nested.lookup("y"), Some(&4));
+        assert_eq!(nested.shadowed_bindings("y").collect::<Vec<_>>(), vec![&3]);
+
+        let local = nested.pop_scope().unwrap();
+        assert_eq!(local.lookup("y"), Some(&3));
+        assert_eq!(local.lookup("x"), Some(&2));
+
+        let global = local.pop_scope().unwrap();
+        assert_eq!(global.depth(), 0);
+        assert_eq!(global.lookup("x"), Some(&1));
+        assert_eq!(global.lookup("y"), None);
+    }
+
+    #[test]
+    fn index_test() {
+        let env = Env::new().bind("a", 1).bind("b", 2).bind("a", 3);
+        let newer = env.bind("b", 20).push_scope().bind("c", 30);
+
+        let index = env.index();
+        assert_eq!(index.get("b").as_deref(), Some(&2));
+        assert_eq!(index.get("a").as_deref(), Some(&3));
+        assert_eq!(index.get("c").as_deref(), None);
+        assert_eq!(index.get("a").as_deref(), Some(&3));
+
+        // an index built on another version answers for that version only
+        let newer_index = newer.index();
+        assert_eq!(newer_index.get("b").as_deref(), Some(&20));
+        assert_eq!(newer_index.get("c").as_deref(), Some(&30));
+        assert_eq!(index.get("b").as_deref(), Some(&2));
+
+        // and it agrees with a plain lookup everywhere
+        let popped = newer.pop_scope().unwrap();
+        let popped_index = popped.index();
+        for key in ["a", "b", "c", "d"] {
+            assert_eq!(popped_index.get(key).as_deref(), popped.lookup(key));
+        }
+    }
+
+    #[test]
+    fn index_reuse_test() {
+        // owned keys, looked up by &str
+        let outer = (0..100).fold(Env::new(), |env, i| env.bind(format!("k{}", i), i));
+        assert_eq!(outer.index().get("k3").as_deref(), Some(&3));
+
+        // the index stays with the version instead of being rebuilt for every use
+        assert!(std::ptr::eq(outer.index(), outer.index()));
+        assert!(std::ptr::eq(outer.index(), outer.clone().index()));
+        let inner = outer.push_scope();
+        assert!(std::ptr::eq(outer.index(), inner.index()));
+        let inner = inner.bind("k3".to_string(), -3);
+        assert_eq!(inner.index().get("k3").as_deref(), Some(&-3));
+        assert_eq!(inner.index().get("k4").as_deref(), Some(&4));
+        let back = inner.pop_scope().unwrap();
+        assert!(std::ptr::eq(outer.index(), back.index()));
+        assert_eq!(back.index().get("k3").as_deref(), Some(&3));
+        assert_eq!(back.index().get("k100").as_deref(), None);
+
+        // values found by an index outlive every version that held them
+        let index = outer.index();
+        let val = index.get("k50").unwrap();
+        drop(back);
+        assert_eq!(*val, 50);
+    }
+}
@@ -1,76 +1,228 @@
+use std::cell::Cell;
 use std::cell::Ref;
 use std::cell::RefCell;
-use std::rc::Rc;
+use std::cell::RefMut;
+#[cfg(feature = "borrow-tracking")]
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::marker::PhantomData;
+use std::panic::Location;
+use std::ptr;
+use std::rc::{Rc, Weak};
 
 // Rc<RefCell<T>> or Arc<RefCell<T>> is a very common pattern because:
 // Rc or Arc provide containers that can be shared, yet they can be only borrowed as shraed
-// references, not mutable references. Mutablility would be avaliable if we put a RefCell<T> inside shared pointer
-type Link<T> = Option<Rc<RefCell<Node<T>>>>;
+// references, not mutable references. Mutablility would be avaliable if we put a RefCell<T> inside shared pointer.
+//
+// a node used to be exactly that, Rc<RefCell<Node<T>>>, with one borrow flag guarding the element and the
+// links together. but the links never need a reference handed out: relinking only ever swaps whole values in
+// and out, which is what Cell does, without a flag to check and without a way to fail. so now only the element
+// sits in a RefCell, since peeks and iterators do hand out references to it, and the links are Cells next to it.
+// it is still one allocation per node, Rc's two counts in front of the node.
+//
+// that does not make the node any smaller. for a usize the old one took six words: the two counts, the flag,
+// the element and the two links. this one takes eight, as two things were added next to the same five:
+// the element sits in an Option, so it can still be taken out of a node that a leaked iterator or cursor holds
+// on to (see into_val), and the node carries the token of its list, so a handle finds out in O(1) which list
+// its node is in (see NodeHandle). an element with a spare bit pattern, like a Box, fits the Option in its own
+// word. shedding the weak count and the flag as well would take unsafe code managing the nodes by hand,
+// which this list does without. benches/fourth_layout.rs measures both layouts
+type Link<T> = Option<Rc<Node<T>>>;
 
+// only next links own their node. a strong prev would make every pair of neighbours a reference cycle,
+// so freeing would rely on Drop popping every node: a panic halfway (say in an element's own drop)
+// would leak whatever was left. with Weak back-pointers the nodes form a plain chain from head,
+// which is freed no matter how the list goes away. left to Rc that would be one nested drop per node,
+// so a node unlinks the rest of the chain itself as it goes (see the Drop below)
 struct Node<T> {
-    val: T,
-    next: Link<T>,
-    prev: Link<T>,
+    // None only once the element has been taken out, after the node left its list (see into_val)
+    val: RefCell<Option<T>>,
+    next: Cell<Link<T>>,
+    prev: Cell<Weak<Node<T>>>,
+    // the list the node belongs to, so a handle can tell whether it is used with the right list.
+    // always Some, the Option only gives the Cell something to take() while it is read
+    owner: Cell<Option<Rc<Owner>>>,
 }
 
-struct List<T> {
+// a token every list allocates: its address is the list's identity, even if the list value moves.
+// appending a whole list would mean retagging every node it brings along, so instead the token of the
+// list that was emptied forwards to the token of the list its nodes went to, and the emptied list gets a new
+// one. a node's list is the token at the end of that chain, and reading it points the node straight there.
+// a split cannot forward, since the nodes end up in two lists: the shorter part is retagged instead, and
+// the longer part keeps the token, whichever list it ends up in (see List::settle)
+struct Owner {
+    forward: Cell<Option<Rc<Owner>>>,
+}
+
+impl Owner {
+    fn new() -> Rc<Owner> {
+        Rc::new(Owner {
+            forward: Cell::new(None),
+        })
+    }
+
+    // the token at the end of the chain, which every token on the way is shortcut to
+    fn root(owner: Rc<Owner>) -> Rc<Owner> {
+        let mut root = owner.clone();
+        while let Some(next) = get(&root.forward) {
+            root = next;
+        }
+        let mut owner = owner;
+        while !Rc::ptr_eq(&owner, &root) {
+            owner = owner.forward.replace(Some(root.clone())).unwrap();
+        }
+        root
+    }
+}
+
+pub struct List<T> {
     head: Link<T>,
     tail: Link<T>,
+    // never forwards anywhere, only the token of a list that was emptied does
+    owner: Rc<Owner>,
+    len: usize,
+    borrows: BorrowLog,
+}
+
+// a copy of what a Cell holds: taken out, cloned and put back. cloning an Rc or a Weak runs no code of ours,
+// so nobody can see the Cell empty in between
+fn get<U: Clone + Default>(cell: &Cell<U>) -> U {
+    let val = cell.take();
+    cell.set(val.clone());
+    val
 }
 
 impl<T> Node<T> {
-    fn new(val: T) -> Rc<RefCell<Self>> {
-        Rc::new(RefCell::new(Node {
-            val,
-            prev: None,
-            next: None,
-        }))
+    fn new(val: T, owner: Rc<Owner>) -> Rc<Self> {
+        Rc::new(Node {
+            val: RefCell::new(Some(val)),
+            prev: Cell::new(Weak::new()),
+            next: Cell::new(None),
+            owner: Cell::new(Some(owner)),
+        })
+    }
+
+    fn next(&self) -> Link<T> {
+        get(&self.next)
+    }
+
+    fn prev(&self) -> Link<T> {
+        get(&self.prev).upgrade()
+    }
+
+    fn set_prev(&self, prev: &Link<T>) {
+        self.prev
+            .set(prev.as_ref().map_or_else(Weak::new, Rc::downgrade));
+    }
+
+    fn owner(&self) -> Rc<Owner> {
+        let owner = Owner::root(get(&self.owner).unwrap());
+        self.owner.set(Some(owner.clone()));
+        owner
+    }
+
+    // the element, as a Ref or RefMut of the T rather than of the Option around it
+    fn borrow(&self) -> Ref<'_, T> {
+        Ref::map(self.val.borrow(), |val| val.as_ref().unwrap())
+    }
+
+    fn borrow_mut(&self) -> RefMut<'_, T> {
+        RefMut::map(self.val.borrow_mut(), |val| val.as_mut().unwrap())
+    }
+
+    // the element of a node no longer linked anywhere
+    fn into_val(mut node: Rc<Self>) -> T {
+        // the list held the only other strong reference, and unlinking dropped it, so normally the node is ours
+        // alone and the element comes out whatever its borrow flag says (a leaked Ref is forgotten with it).
+        // an iterator or cursor that was leaked still holds a clone of its nodes though. the element is taken
+        // out of the shared node then, and only the empty node is left to the leak
+        match Rc::get_mut(&mut node) {
+            Some(node) => node.val.get_mut().take(),
+            None => node.val.borrow_mut().take(),
+        }
+        .unwrap()
+    }
+}
+
+// dropping a node drops its next link, which drops the next node and so on: a long enough chain overflows
+// the stack. so the chain is taken apart in a loop instead, for as long as this is the last strong
+// reference to the following node. this runs whenever a chain is dropped whole, which the list's own Drop
+// does not do, but unwinding out of it does (when an element panics in drop)
+impl<T> Drop for Node<T> {
+    fn drop(&mut self) {
+        let mut link = self.next.take();
+        while let Some(node) = link {
+            link = match Rc::strong_count(&node) {
+                1 => node.next.take(),
+                _ => None,
+            };
+        }
     }
 }
 
 impl<T> List<T> {
-    fn new() -> List<T> {
+    pub fn new() -> List<T> {
         List::<T> {
             head: None,
             tail: None,
+            owner: Owner::new(),
+            len: 0,
+            borrows: BorrowLog::default(),
         }
     }
 
-    fn push_front(&mut self, val: T) {
-        let new_head = Node::new(val);
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn push_front(&mut self, val: T) {
+        self.link_front(Node::new(val, self.owner.clone()));
+    }
+
+    pub fn push_back(&mut self, val: T) {
+        self.link_back(Node::new(val, self.owner.clone()));
+    }
+
+    fn link_front(&mut self, new_head: Rc<Node<T>>) {
+        self.len += 1;
         match self.head.take() {
             None => {
                 self.head = Some(new_head.clone());
                 self.tail = Some(new_head);
             }
             Some(old_head) => {
-                old_head.borrow_mut().prev = Some(new_head.clone());
-                new_head.borrow_mut().next = Some(old_head);
+                old_head.prev.set(Rc::downgrade(&new_head));
+                new_head.next.set(Some(old_head));
                 self.head = Some(new_head);
             }
         }
     }
 
-    fn push_back(&mut self, val: T) {
-        let new_tail = Node::new(val);
+    fn link_back(&mut self, new_tail: Rc<Node<T>>) {
+        self.len += 1;
         match self.tail.take() {
             None => {
                 self.tail = Some(new_tail.clone());
                 self.head = Some(new_tail);
             }
             Some(old_tail) => {
-                old_tail.borrow_mut().next = Some(new_tail.clone());
-                new_tail.borrow_mut().prev = Some(old_tail);
+                old_tail.next.set(Some(new_tail.clone()));
+                new_tail.prev.set(Rc::downgrade(&old_tail));
                 self.tail = Some(new_tail);
             }
         }
     }
 
-    fn pop_front(&mut self) -> Option<T> {
+    pub fn pop_front(&mut self) -> Option<T> {
         self.head.take().map(|old_head| {
-            match old_head.borrow_mut().next.take() {
+            match old_head.next.take() {
                 Some(new_head) => {
-                    new_head.borrow_mut().prev.take();
+                    new_head.prev.set(Weak::new());
                     self.head = Some(new_head);
                 }
                 None => {
@@ -80,22 +232,26 @@ impl<T> List<T> {
                     self.tail.take();
                 }
             }
-            Rc::try_unwrap(old_head).ok().unwrap().into_inner().val
+            self.len -= 1;
+            self.borrows.forget(&old_head);
+            Node::into_val(old_head)
         })
     }
 
-    fn pop_back(&mut self) -> Option<T> {
+    pub fn pop_back(&mut self) -> Option<T> {
         self.tail.take().map(|old_tail| {
-            match old_tail.borrow_mut().prev.take() {
+            match old_tail.prev.take().upgrade() {
                 Some(new_tail) => {
-                    new_tail.borrow_mut().next.take();
+                    new_tail.next.take();
                     self.tail = Some(new_tail);
                 }
                 None => {
                     self.head.take();
                 }
             }
-            Rc::try_unwrap(old_tail).ok().unwrap().into_inner().val
+            self.len -= 1;
+            self.borrows.forget(&old_tail);
+            Node::into_val(old_tail)
         })
     }
 
@@ -103,16 +259,547 @@ impl<T> List<T> {
     // however, a trival shared reference &T cannot be retrieved via RefCell<T>
     // because &T cannot keep track of every reference generated from RefCell<T>
     // so if we want to get reference, use Ref<T> instead of &T
-    fn peek_front(&self) -> Option<Ref<T>> {
-        self.head  // Option<Rc<RefCell<Node<T>>>>
-            .as_ref()  // Option<&Rc<RefCell<Node<T>>>>
-            .map(|node| Ref::map(node.borrow(), |node| &node.val))
+    #[track_caller]
+    pub fn peek_front(&self) -> Option<Ref<T>> {
+        let node = self
+            .head // Option<Rc<Node<T>>>
+            .as_ref()?; // Option<&Rc<Node<T>>>
+        Some(self.shared(node))
+    }
+
+    #[track_caller]
+    pub fn peek_back(&self) -> Option<Ref<T>> {
+        let node = self
+            .tail // Option<Rc<Node<T>>>
+            .as_ref()?; // Option<&Rc<Node<T>>>
+        Some(self.shared(node))
+    }
+
+    // RefMut<T> is to RefCell<T> what &mut T is to T, and &mut self makes sure nobody else peeks meanwhile
+    #[track_caller]
+    pub fn peek_front_mut(&mut self) -> Option<RefMut<'_, T>> {
+        let node = self.head.as_ref()?;
+        Some(self.exclusive(node))
     }
 
-    fn peek_back(&self) -> Option<Ref<T>> {
-        self.tail// Option<Rc<RefCell<Node<T>>>>
-            .as_ref() // Option<&Rc<RefCell<Node<T>>>>
-            .map(|node| Ref::map(node.borrow(), |node| &node.val))
+    #[track_caller]
+    pub fn peek_back_mut(&mut self) -> Option<RefMut<'_, T>> {
+        let node = self.tail.as_ref()?;
+        Some(self.exclusive(node))
+    }
+
+    // every Ref or RefMut handed out goes through these two, which note where it was taken.
+    // a Ref taken while others are still around is not noted, so the one on record is the oldest
+    #[track_caller]
+    fn shared<'b>(&self, node: &'b Node<T>) -> Ref<'b, T> {
+        let first = node.val.try_borrow_mut().is_ok();
+        let val = node.borrow();
+        if first {
+            self.borrows.record(node);
+        }
+        val
+    }
+
+    #[track_caller]
+    fn exclusive<'b>(&self, node: &'b Node<T>) -> RefMut<'b, T> {
+        let val = node.borrow_mut();
+        self.borrows.record(node);
+        val
+    }
+}
+
+// non-panicking borrows
+// a Ref from peek_front is tied to a shared borrow of the list, so the borrow checker keeps it from living
+// across a peek_front_mut. but a Ref can still outlive that borrow by being leaked (mem::forget, or a Ref kept
+// inside a leaked value), and then the next borrow_mut of its element panics from inside the list. the try_
+// variants check the element first, and on a conflict return an error naming the operation and the position
+// of the node. every call that borrows an element has one: the peeks here, NodeHandle::try_get(_mut),
+// the cursors' try_current and try_peek_next/prev, and try_next(_back) on the borrowing iterators.
+// pushes, pops and the other relinking calls never borrow an element, since the links sit outside its
+// RefCell, so they cannot fail this way and have none.
+//
+// with the borrow-tracking feature the list also remembers, per node, where the last Ref or RefMut of it was
+// handed out (through peeks, handles, cursors and the try_ calls), and the error carries that location.
+// it is meant for debug builds: it costs a map insert on every borrow
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ListBorrowError {
+    pub op: &'static str,
+    // the position of the element in the list, counted from the front
+    pub index: usize,
+    // where the conflicting borrow was taken, only known with the borrow-tracking feature
+    pub borrowed_at: Option<&'static Location<'static>>,
+}
+
+impl fmt::Display for ListBorrowError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: the element at index {} is already borrowed",
+            self.op, self.index
+        )?;
+        match self.borrowed_at {
+            Some(at) => write!(f, " (borrowed at {})", at),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Error for ListBorrowError {}
+
+impl<T> List<T> {
+    #[track_caller]
+    pub fn try_peek_front(&self) -> Result<Option<Ref<'_, T>>, ListBorrowError> {
+        self.check("peek_front", self.head.as_deref(), false)?;
+        Ok(self.peek_front())
+    }
+
+    #[track_caller]
+    pub fn try_peek_back(&self) -> Result<Option<Ref<'_, T>>, ListBorrowError> {
+        self.check("peek_back", self.tail.as_deref(), false)?;
+        Ok(self.peek_back())
+    }
+
+    #[track_caller]
+    pub fn try_peek_front_mut(&mut self) -> Result<Option<RefMut<'_, T>>, ListBorrowError> {
+        self.check("peek_front_mut", self.head.as_deref(), true)?;
+        Ok(self.peek_front_mut())
+    }
+
+    #[track_caller]
+    pub fn try_peek_back_mut(&mut self) -> Result<Option<RefMut<'_, T>>, ListBorrowError> {
+        self.check("peek_back_mut", self.tail.as_deref(), true)?;
+        Ok(self.peek_back_mut())
+    }
+
+    // whether the element of the node, if any, could be borrowed shared (or exclusively) right now
+    fn check(
+        &self,
+        op: &'static str,
+        node: Option<&Node<T>>,
+        exclusive: bool,
+    ) -> Result<(), ListBorrowError> {
+        let Some(node) = node else {
+            return Ok(());
+        };
+        let free = match exclusive {
+            true => node.val.try_borrow_mut().is_ok(),
+            false => node.val.try_borrow().is_ok(),
+        };
+        match free {
+            true => Ok(()),
+            false => Err(ListBorrowError {
+                op,
+                index: self.index_of(node),
+                borrowed_at: self.borrows.lookup(node),
+            }),
+        }
+    }
+
+    // the position of one of our nodes, walking from the head. only errors need it
+    fn index_of(&self, node: &Node<T>) -> usize {
+        let mut link = self.head.clone();
+        let mut index = 0;
+        while let Some(other) = link.filter(|other| !ptr::eq(&**other, node)) {
+            link = other.next();
+            index += 1;
+        }
+        index
+    }
+}
+
+// where the borrows handed out were taken, by node address. a node's entry is overwritten whenever it is
+// borrowed again after being free, and removed when the node leaves the list, so an entry found for a node
+// that is still borrowed belongs to its oldest outstanding borrow. entries move along with their nodes:
+// appending or splicing a list brings all of its entries, and a split moves those of the nodes it retags
+// (see List::settle). without the feature this is empty and does nothing
+#[derive(Default)]
+struct BorrowLog {
+    #[cfg(feature = "borrow-tracking")]
+    at: RefCell<HashMap<usize, &'static Location<'static>>>,
+}
+
+#[cfg(feature = "borrow-tracking")]
+impl BorrowLog {
+    #[track_caller]
+    fn record<T>(&self, node: &Node<T>) {
+        self.at
+            .borrow_mut()
+            .insert(node as *const Node<T> as usize, Location::caller());
+    }
+
+    fn lookup<T>(&self, node: &Node<T>) -> Option<&'static Location<'static>> {
+        self.at
+            .borrow()
+            .get(&(node as *const Node<T> as usize))
+            .copied()
+    }
+
+    fn forget<T>(&self, node: &Node<T>) {
+        self.at
+            .borrow_mut()
+            .remove(&(node as *const Node<T> as usize));
+    }
+
+    // the entry of a node that moves over to us from the list of from
+    fn take<T>(&self, from: &BorrowLog, node: &Node<T>) {
+        let key = node as *const Node<T> as usize;
+        if let Some(at) = from.at.borrow_mut().remove(&key) {
+            self.at.borrow_mut().insert(key, at);
+        }
+    }
+
+    // the entries of all the nodes of from's list, which move over to us
+    fn take_all(&self, from: &BorrowLog) {
+        self.at.borrow_mut().extend(from.at.borrow_mut().drain());
+    }
+}
+
+#[cfg(not(feature = "borrow-tracking"))]
+impl BorrowLog {
+    fn record<T>(&self, _: &Node<T>) {}
+
+    fn lookup<T>(&self, _: &Node<T>) -> Option<&'static Location<'static>> {
+        None
+    }
+
+    fn forget<T>(&self, _: &Node<T>) {}
+
+    fn take<T>(&self, _: &BorrowLog, _: &Node<T>) {}
+
+    fn take_all(&self, _: &BorrowLog) {}
+}
+
+// node handles
+// a handle is a Weak pointer to one node, so holding it keeps nothing alive: once the node is popped or
+// removed the handle simply stops resolving. every operation takes the list the node should be in and checks
+// the node's owner token against it, so a handle used with the wrong list fails instead of relinking
+// nodes across lists. with both checks done up front all of them are O(1)
+pub struct NodeHandle<T> {
+    node: Weak<Node<T>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HandleError {
+    // the node has been popped or removed, possibly together with its whole list
+    Removed,
+    // the node is alive but in another list
+    ForeignList,
+    // only from try_get and try_get_mut: the node is ours, but its element is borrowed in a conflicting way
+    Borrowed(ListBorrowError),
+}
+
+impl fmt::Display for HandleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HandleError::Removed => write!(f, "node has been removed"),
+            HandleError::ForeignList => write!(f, "node belongs to another list"),
+            HandleError::Borrowed(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl Error for HandleError {}
+
+impl From<ListBorrowError> for HandleError {
+    fn from(err: ListBorrowError) -> Self {
+        HandleError::Borrowed(err)
+    }
+}
+
+impl<T> Clone for NodeHandle<T> {
+    fn clone(&self) -> Self {
+        NodeHandle {
+            node: self.node.clone(),
+        }
+    }
+}
+
+impl<T> List<T> {
+    pub fn push_front_handle(&mut self, val: T) -> NodeHandle<T> {
+        let node = Node::new(val, self.owner.clone());
+        let handle = NodeHandle::new(&node);
+        self.link_front(node);
+        handle
+    }
+
+    pub fn push_back_handle(&mut self, val: T) -> NodeHandle<T> {
+        let node = Node::new(val, self.owner.clone());
+        let handle = NodeHandle::new(&node);
+        self.link_back(node);
+        handle
+    }
+
+    fn resolve(&self, handle: &NodeHandle<T>) -> Result<Rc<Node<T>>, HandleError> {
+        let node = handle.node.upgrade().ok_or(HandleError::Removed)?;
+        if !Rc::ptr_eq(&node.owner(), &self.owner) {
+            return Err(HandleError::ForeignList);
+        }
+        Ok(node)
+    }
+
+    // takes the node out of the chain, leaving it with no neighbours
+    fn unlink(&mut self, node: &Rc<Node<T>>) {
+        self.len -= 1;
+        self.borrows.forget(node);
+        let prev = node.prev.take().upgrade();
+        let next = node.next.take();
+        match &next {
+            Some(next) => next.set_prev(&prev),
+            None => self.tail = prev.clone(),
+        }
+        match prev {
+            Some(prev) => prev.next.set(next),
+            None => self.head = next,
+        }
+    }
+
+    // puts an unlinked node between prev and next, which must be neighbours (or the ends of the list)
+    fn link_between(&mut self, prev: Link<T>, next: Link<T>, node: Rc<Node<T>>) {
+        self.len += 1;
+        self.link_chain(prev, next, node.clone(), node);
+    }
+
+    // the same for a whole chain of nodes already linked from first to last, which the caller has counted in
+    fn link_chain(&mut self, prev: Link<T>, next: Link<T>, first: Rc<Node<T>>, last: Rc<Node<T>>) {
+        first.set_prev(&prev);
+        last.next.set(next.clone());
+        match &next {
+            Some(next) => next.prev.set(Rc::downgrade(&last)),
+            None => self.tail = Some(last),
+        }
+        match prev {
+            Some(prev) => prev.next.set(Some(first)),
+            None => self.head = Some(first),
+        }
+    }
+
+    // empties other, handing out its first and last node with the chain between them intact, for us to link in.
+    // its nodes count as ours from now on: its token forwards to ours and it gets a new one, so this is O(1)
+    fn absorb(&mut self, other: &mut List<T>) -> Option<Chain<T>> {
+        let first = other.head.take()?;
+        let last = other.tail.take().unwrap();
+        let owner = std::mem::replace(&mut other.owner, Owner::new());
+        owner.forward.set(Some(self.owner.clone()));
+        self.len += std::mem::take(&mut other.len);
+        self.borrows.take_all(&other.borrows);
+        Some((first, last))
+    }
+
+    // after split was cut off from us, with a new token, while its nodes are still tagged as ours (or with
+    // tokens that forward to ours). the shorter of the two lists has its nodes retagged, so a split costs
+    // no more than walking to the split point from the closer end. when that is us, split takes over our
+    // token (and our borrow log) first: the nodes it has are the ones still tagged with it
+    fn settle(&mut self, split: &mut List<T>) {
+        let (shorter, longer) = match split.len <= self.len {
+            true => (split, self),
+            false => {
+                std::mem::swap(&mut self.owner, &mut split.owner);
+                std::mem::swap(&mut self.borrows, &mut split.borrows);
+                (self, split)
+            }
+        };
+        let mut link = shorter.head.clone();
+        while let Some(node) = link {
+            node.owner.set(Some(shorter.owner.clone()));
+            shorter.borrows.take(&longer.borrows, &node);
+            link = node.next();
+        }
+    }
+
+    // a list of a chain of len nodes that has been cut loose from both sides
+    fn from_chain(first: Rc<Node<T>>, last: Rc<Node<T>>, len: usize) -> List<T> {
+        let mut list = List::new();
+        list.len = len;
+        list.head = Some(first);
+        list.tail = Some(last);
+        list
+    }
+
+    // the node at index at, walking in from whichever end is closer
+    fn node_at(&self, at: usize) -> Link<T> {
+        if at >= self.len {
+            return None;
+        }
+        match at < self.len / 2 {
+            true => (0..at).try_fold(self.head.clone()?, |node, _| node.next()),
+            false => (at + 1..self.len).try_fold(self.tail.clone()?, |node, _| node.prev()),
+        }
+    }
+}
+
+impl<T> NodeHandle<T> {
+    fn new(node: &Rc<Node<T>>) -> Self {
+        NodeHandle {
+            node: Rc::downgrade(node),
+        }
+    }
+
+    pub fn remove(&self, list: &mut List<T>) -> Result<T, HandleError> {
+        let node = list.resolve(self)?;
+        list.unlink(&node);
+        Ok(Node::into_val(node))
+    }
+
+    pub fn insert_before(&self, list: &mut List<T>, val: T) -> Result<NodeHandle<T>, HandleError> {
+        let at = list.resolve(self)?;
+        let prev = at.prev();
+        let node = Node::new(val, list.owner.clone());
+        let handle = NodeHandle::new(&node);
+        list.link_between(prev, Some(at), node);
+        Ok(handle)
+    }
+
+    pub fn insert_after(&self, list: &mut List<T>, val: T) -> Result<NodeHandle<T>, HandleError> {
+        let at = list.resolve(self)?;
+        let next = at.next();
+        let node = Node::new(val, list.owner.clone());
+        let handle = NodeHandle::new(&node);
+        list.link_between(Some(at), next, node);
+        Ok(handle)
+    }
+
+    pub fn move_to_front(&self, list: &mut List<T>) -> Result<(), HandleError> {
+        let node = list.resolve(self)?;
+        list.unlink(&node);
+        let head = list.head.clone();
+        list.link_between(None, head, node);
+        Ok(())
+    }
+
+    pub fn move_to_back(&self, list: &mut List<T>) -> Result<(), HandleError> {
+        let node = list.resolve(self)?;
+        list.unlink(&node);
+        let tail = list.tail.clone();
+        list.link_between(tail, None, node);
+        Ok(())
+    }
+
+    // the node is owned by the list, so like the borrowing iterators the Ref can live as long as the list borrow
+    #[track_caller]
+    pub fn get<'a>(&self, list: &'a List<T>) -> Result<Ref<'a, T>, HandleError> {
+        let node = list.resolve(self)?;
+        // SAFETY: resolve found the node in list, which is borrowed for 'a
+        Ok(list.shared(unsafe { node_ref(&node) }))
+    }
+
+    #[track_caller]
+    pub fn get_mut<'a>(&self, list: &'a mut List<T>) -> Result<RefMut<'a, T>, HandleError> {
+        let node = list.resolve(self)?;
+        // SAFETY: as in get
+        Ok(list.exclusive(unsafe { node_ref(&node) }))
+    }
+
+    #[track_caller]
+    pub fn try_get<'a>(&self, list: &'a List<T>) -> Result<Ref<'a, T>, HandleError> {
+        let node = list.resolve(self)?;
+        list.check("get", Some(&node), false)?;
+        // SAFETY: as in get
+        Ok(list.shared(unsafe { node_ref(&node) }))
+    }
+
+    #[track_caller]
+    pub fn try_get_mut<'a>(&self, list: &'a mut List<T>) -> Result<RefMut<'a, T>, HandleError> {
+        let node = list.resolve(self)?;
+        list.check("get_mut", Some(&node), true)?;
+        // SAFETY: as in get
+        Ok(list.exclusive(unsafe { node_ref(&node) }))
+    }
+}
+
+// whole lists
+// appending relinks the two ends and forwards the emptied list's token (see Owner), so it costs the same
+// however long either list is. splitting and rotating have to find a node by index first, which they do from
+// the closer end, and a split retags the shorter half
+impl<T> List<T> {
+    // moves all of other to our back, leaving it empty
+    pub fn append(&mut self, other: &mut List<T>) {
+        if let Some((first, last)) = self.absorb(other) {
+            let tail = self.tail.clone();
+            self.link_chain(tail, None, first, last);
+        }
+    }
+
+    // moves all of other to our front, leaving it empty
+    pub fn prepend_list(&mut self, other: &mut List<T>) {
+        if let Some((first, last)) = self.absorb(other) {
+            let head = self.head.clone();
+            self.link_chain(None, head, first, last);
+        }
+    }
+
+    // everything from index at on as a new list, panics if at is past the end
+    pub fn split_off(&mut self, at: usize) -> List<T> {
+        assert!(
+            at <= self.len,
+            "split_off: {} is past the end ({})",
+            at,
+            self.len
+        );
+        if at == 0 {
+            return std::mem::take(self);
+        }
+        let Some(first) = self.node_at(at) else {
+            return List::new();
+        };
+        let last = first.prev.take().upgrade().unwrap();
+        last.next.take();
+        let mut split = List::new();
+        split.head = Some(first);
+        split.tail = self.tail.replace(last);
+        split.len = self.len - at;
+        self.len = at;
+        self.settle(&mut split);
+        split
+    }
+
+    // moves the first n elements to the back, panics if n is more than the length
+    pub fn rotate_left(&mut self, n: usize) {
+        assert!(
+            n <= self.len,
+            "rotate_left: {} is more than the length ({})",
+            n,
+            self.len
+        );
+        let Some(new_head) = self.node_at(n).filter(|_| n > 0) else {
+            return;
+        };
+        let new_tail = new_head.prev.take().upgrade().unwrap();
+        new_tail.next.take();
+        let old_head = self.head.replace(new_head).unwrap();
+        let old_tail = self.tail.replace(new_tail).unwrap();
+        old_head.prev.set(Rc::downgrade(&old_tail));
+        old_tail.next.set(Some(old_head));
+    }
+
+    // moves the last n elements to the front, panics if n is more than the length
+    pub fn rotate_right(&mut self, n: usize) {
+        assert!(
+            n <= self.len,
+            "rotate_right: {} is more than the length ({})",
+            n,
+            self.len
+        );
+        self.rotate_left(self.len - n);
+    }
+
+    // swaps next and prev in every node, the nodes and their handles stay where they are.
+    // next is the link that owns, so the walk keeps the node it just turned around in prev until
+    // the following node's next owns it
+    pub fn reverse(&mut self) {
+        let mut prev: Link<T> = None;
+        let mut link = self.head.clone();
+        while let Some(node) = link {
+            link = node.next.replace(prev.take());
+            node.set_prev(&link);
+            prev = Some(node);
+        }
+        std::mem::swap(&mut self.head, &mut self.tail);
+    }
+}
+
+impl<T> Default for List<T> {
+    fn default() -> Self {
+        List::new()
     }
 }
 
@@ -143,10 +830,472 @@ impl<T> DoubleEndedIterator for IntoIter<T> {
     }
 }
 
+// borrowing iterators
+// a Ref<T> has to borrow from a RefCell that outlives it, but the RefCells here sit inside Rc nodes that
+// the iterator only holds clones of. the nodes are owned by the list though, and as long as the list is
+// borrowed (shared for iter, exclusive for iter_mut) nothing can unlink them: push and pop need &mut List.
+// so for 'a every node is alive and the RefCell inside it can be borrowed for 'a (see node_ref).
+//
+// front and back are the next nodes to visit from either end, both None once they have crossed.
+// moving on reads only the links, which are outside the element's RefCell, so iterating never
+// borrows an element that the caller still holds
+pub struct Iter<'a, T> {
+    front: Link<T>,
+    back: Link<T>,
+    // only read, for the try_ calls
+    list: &'a List<T>,
+}
+
+pub struct IterMut<'a, T> {
+    front: Link<T>,
+    back: Link<T>,
+    list: &'a List<T>,
+    exclusive: PhantomData<&'a mut List<T>>,
+}
+
+impl<T> List<T> {
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter {
+            front: self.head.clone(),
+            back: self.tail.clone(),
+            list: self,
+        }
+    }
+
+    pub fn iter_mut(&mut self) -> IterMut<'_, T> {
+        IterMut {
+            front: self.head.clone(),
+            back: self.tail.clone(),
+            list: self,
+            exclusive: PhantomData,
+        }
+    }
+}
+
+// the first and last node of a run of linked nodes
+type Chain<T> = (Rc<Node<T>>, Rc<Node<T>>);
+
+// takes the node at one end and moves that end one step inwards, or closes both ends when they met
+fn step<T>(from: &mut Link<T>, other: &mut Link<T>, inwards: fn(&Node<T>) -> Link<T>) -> Link<T> {
+    let node = from.take()?;
+    if other.as_ref().is_some_and(|other| Rc::ptr_eq(other, &node)) {
+        *other = None;
+    } else {
+        *from = inwards(&node);
+    }
+    Some(node)
+}
+
+// a reference to a node that lives for 'a, rather than as long as the Rc clone it was reached through.
+//
+// SAFETY: the caller makes sure that the node is linked into a list which stays borrowed for 'a.
+// a node is only ever unlinked through &mut List, so for 'a the list's own strong reference (the head,
+// or the next link of the node before it) stays where it is and keeps the node alive at the same address.
+// the reference is shared, and everything in a node that is written to sits in a Cell or RefCell
+unsafe fn node_ref<'a, T>(node: &Rc<Node<T>>) -> &'a Node<T> {
+    &*Rc::as_ptr(node)
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = Ref<'a, T>;
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = step(&mut self.front, &mut self.back, Node::next)?;
+        // SAFETY: the node came from self.list, which is borrowed for 'a
+        Some(unsafe { node_ref(&node) }.borrow())
+    }
+}
+
+impl<T> DoubleEndedIterator for Iter<'_, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let node = step(&mut self.back, &mut self.front, Node::prev)?;
+        // SAFETY: as in next
+        Some(unsafe { node_ref(&node) }.borrow())
+    }
+}
+
+impl<'a, T> Iterator for IterMut<'a, T> {
+    type Item = RefMut<'a, T>;
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = step(&mut self.front, &mut self.back, Node::next)?;
+        // SAFETY: the node came from self.list, which is borrowed (exclusively) for 'a
+        Some(unsafe { node_ref(&node) }.borrow_mut())
+    }
+}
+
+impl<T> DoubleEndedIterator for IterMut<'_, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let node = step(&mut self.back, &mut self.front, Node::prev)?;
+        // SAFETY: as in next
+        Some(unsafe { node_ref(&node) }.borrow_mut())
+    }
+}
+
+// next and next_back without the panic. the iterator moves past an element that is already borrowed
+// just the same, so the caller can carry on with the rest
+impl<'a, T> Iter<'a, T> {
+    #[track_caller]
+    pub fn try_next(&mut self) -> Option<Result<Ref<'a, T>, ListBorrowError>> {
+        let node = step(&mut self.front, &mut self.back, Node::next)?;
+        Some(self.try_shared("next", &node))
+    }
+
+    #[track_caller]
+    pub fn try_next_back(&mut self) -> Option<Result<Ref<'a, T>, ListBorrowError>> {
+        let node = step(&mut self.back, &mut self.front, Node::prev)?;
+        Some(self.try_shared("next_back", &node))
+    }
+
+    #[track_caller]
+    fn try_shared(
+        &self,
+        op: &'static str,
+        node: &Rc<Node<T>>,
+    ) -> Result<Ref<'a, T>, ListBorrowError> {
+        self.list.check(op, Some(node), false)?;
+        // SAFETY: the node came from self.list, which is borrowed for 'a
+        Ok(self.list.shared(unsafe { node_ref(node) }))
+    }
+}
+
+impl<'a, T> IterMut<'a, T> {
+    #[track_caller]
+    pub fn try_next(&mut self) -> Option<Result<RefMut<'a, T>, ListBorrowError>> {
+        let node = step(&mut self.front, &mut self.back, Node::next)?;
+        Some(self.try_exclusive("next_mut", &node))
+    }
+
+    #[track_caller]
+    pub fn try_next_back(&mut self) -> Option<Result<RefMut<'a, T>, ListBorrowError>> {
+        let node = step(&mut self.back, &mut self.front, Node::prev)?;
+        Some(self.try_exclusive("next_back_mut", &node))
+    }
+
+    #[track_caller]
+    fn try_exclusive(
+        &self,
+        op: &'static str,
+        node: &Rc<Node<T>>,
+    ) -> Result<RefMut<'a, T>, ListBorrowError> {
+        self.list.check(op, Some(node), true)?;
+        // SAFETY: the node came from self.list, which is borrowed exclusively for 'a
+        Ok(self.list.exclusive(unsafe { node_ref(node) }))
+    }
+}
+
+// cursors
+// a cursor points at a node, or at the "ghost" position between tail and head where it starts over: moving
+// next from the tail or prev from the head lands on the ghost, and moving on from there wraps around.
+// like the iterators, the cursor holds an Rc clone of its node and the list stays borrowed for the cursor's
+// lifetime, so that node cannot leave the list behind its back, except through the cursor itself, which moves
+// off a node before removing it. it also keeps the index of its node (the length of the list on the ghost),
+// as std's LinkedList cursors do, so a split knows how long both halves are without counting them.
+// CursorMut edits go through the same linking helpers as the handles, which update head and tail together
+// with the neighbours
+pub struct Cursor<'a, T> {
+    current: Link<T>,
+    index: usize,
+    list: &'a List<T>,
+}
+
+pub struct CursorMut<'a, T> {
+    current: Link<T>,
+    index: usize,
+    list: &'a mut List<T>,
+}
+
+impl<T> List<T> {
+    pub fn cursor_front(&self) -> Cursor<'_, T> {
+        Cursor {
+            current: self.head.clone(),
+            index: 0,
+            list: self,
+        }
+    }
+
+    pub fn cursor_back(&self) -> Cursor<'_, T> {
+        Cursor {
+            current: self.tail.clone(),
+            index: self.len.saturating_sub(1),
+            list: self,
+        }
+    }
+
+    pub fn cursor_front_mut(&mut self) -> CursorMut<'_, T> {
+        CursorMut {
+            current: self.head.clone(),
+            index: 0,
+            list: self,
+        }
+    }
+
+    pub fn cursor_back_mut(&mut self) -> CursorMut<'_, T> {
+        CursorMut {
+            current: self.tail.clone(),
+            index: self.len.saturating_sub(1),
+            list: self,
+        }
+    }
+}
+
+// the node after current, where the ghost is followed by the head
+fn following<T>(list: &List<T>, current: &Link<T>) -> Link<T> {
+    match current {
+        Some(node) => node.next(),
+        None => list.head.clone(),
+    }
+}
+
+// the node before current, where the ghost is preceded by the tail
+fn preceding<T>(list: &List<T>, current: &Link<T>) -> Link<T> {
+    match current {
+        Some(node) => node.prev(),
+        None => list.tail.clone(),
+    }
+}
+
+// the index after moving one step either way, for a list of len elements where index len is the ghost
+fn index_after(index: usize, len: usize) -> usize {
+    match index == len {
+        true => 0,
+        false => index + 1,
+    }
+}
+
+fn index_before(index: usize, len: usize) -> usize {
+    match index {
+        0 => len,
+        _ if index == len => len - 1,
+        _ => index - 1,
+    }
+}
+
+impl<'a, T> Cursor<'a, T> {
+    // None on the ghost
+    pub fn index(&self) -> Option<usize> {
+        self.current.as_ref().map(|_| self.index)
+    }
+
+    pub fn move_next(&mut self) {
+        self.current = following(self.list, &self.current);
+        self.index = index_after(self.index, self.list.len);
+    }
+
+    pub fn move_prev(&mut self) {
+        self.current = preceding(self.list, &self.current);
+        self.index = index_before(self.index, self.list.len);
+    }
+
+    // None on the ghost
+    #[track_caller]
+    pub fn current(&self) -> Option<Ref<'a, T>> {
+        let node = self.current.as_ref()?;
+        // SAFETY: the cursor's nodes are in self.list, which is borrowed for 'a
+        Some(self.list.shared(unsafe { node_ref(node) }))
+    }
+
+    #[track_caller]
+    pub fn peek_next(&self) -> Option<Ref<'a, T>> {
+        let node = following(self.list, &self.current)?;
+        // SAFETY: as in current
+        Some(self.list.shared(unsafe { node_ref(&node) }))
+    }
+
+    #[track_caller]
+    pub fn peek_prev(&self) -> Option<Ref<'a, T>> {
+        let node = preceding(self.list, &self.current)?;
+        // SAFETY: as in current
+        Some(self.list.shared(unsafe { node_ref(&node) }))
+    }
+
+    #[track_caller]
+    pub fn try_current(&self) -> Result<Option<Ref<'a, T>>, ListBorrowError> {
+        self.list.check("current", self.current.as_deref(), false)?;
+        Ok(self.current())
+    }
+
+    #[track_caller]
+    pub fn try_peek_next(&self) -> Result<Option<Ref<'a, T>>, ListBorrowError> {
+        let node = following(self.list, &self.current);
+        self.list.check("peek_next", node.as_deref(), false)?;
+        Ok(self.peek_next())
+    }
+
+    #[track_caller]
+    pub fn try_peek_prev(&self) -> Result<Option<Ref<'a, T>>, ListBorrowError> {
+        let node = preceding(self.list, &self.current);
+        self.list.check("peek_prev", node.as_deref(), false)?;
+        Ok(self.peek_prev())
+    }
+}
+
+impl<T> CursorMut<'_, T> {
+    // None on the ghost
+    pub fn index(&self) -> Option<usize> {
+        self.current.as_ref().map(|_| self.index)
+    }
+
+    pub fn move_next(&mut self) {
+        self.current = following(self.list, &self.current);
+        self.index = index_after(self.index, self.list.len);
+    }
+
+    pub fn move_prev(&mut self) {
+        self.current = preceding(self.list, &self.current);
+        self.index = index_before(self.index, self.list.len);
+    }
+
+    #[track_caller]
+    pub fn current(&mut self) -> Option<RefMut<'_, T>> {
+        let node = self.current.as_ref()?;
+        Some(self.list.exclusive(node))
+    }
+
+    #[track_caller]
+    pub fn peek_next(&mut self) -> Option<RefMut<'_, T>> {
+        let node = following(self.list, &self.current)?;
+        // SAFETY: the node is in self.list, which is borrowed for as long as self is
+        Some(self.list.exclusive(unsafe { node_ref(&node) }))
+    }
+
+    #[track_caller]
+    pub fn peek_prev(&mut self) -> Option<RefMut<'_, T>> {
+        let node = preceding(self.list, &self.current)?;
+        // SAFETY: as in peek_next
+        Some(self.list.exclusive(unsafe { node_ref(&node) }))
+    }
+
+    #[track_caller]
+    pub fn try_current(&mut self) -> Result<Option<RefMut<'_, T>>, ListBorrowError> {
+        self.list
+            .check("current_mut", self.current.as_deref(), true)?;
+        Ok(self.current())
+    }
+
+    #[track_caller]
+    pub fn try_peek_next(&mut self) -> Result<Option<RefMut<'_, T>>, ListBorrowError> {
+        let node = following(self.list, &self.current);
+        self.list.check("peek_next_mut", node.as_deref(), true)?;
+        Ok(self.peek_next())
+    }
+
+    #[track_caller]
+    pub fn try_peek_prev(&mut self) -> Result<Option<RefMut<'_, T>>, ListBorrowError> {
+        let node = preceding(self.list, &self.current);
+        self.list.check("peek_prev_mut", node.as_deref(), true)?;
+        Ok(self.peek_prev())
+    }
+
+    // a read-only cursor at the same position, for as long as this one is not used
+    pub fn as_cursor(&self) -> Cursor<'_, T> {
+        Cursor {
+            current: self.current.clone(),
+            index: self.index,
+            list: self.list,
+        }
+    }
+
+    // before the ghost is the back of the list
+    pub fn insert_before(&mut self, val: T) {
+        let prev = preceding(self.list, &self.current);
+        let next = self.current.clone();
+        let node = Node::new(val, self.list.owner.clone());
+        self.list.link_between(prev, next, node);
+        self.index += 1;
+    }
+
+    // after the ghost is the front of the list
+    pub fn insert_after(&mut self, val: T) {
+        let prev = self.current.clone();
+        let next = following(self.list, &self.current);
+        let node = Node::new(val, self.list.owner.clone());
+        self.list.link_between(prev, next, node);
+        if self.current.is_none() {
+            self.index += 1;
+        }
+    }
+
+    // removes the current element and moves on to the next one, which takes over its index.
+    // nothing happens on the ghost
+    pub fn remove_current(&mut self) -> Option<T> {
+        let node = self.current.take()?;
+        self.current = node.next();
+        self.list.unlink(&node);
+        Some(Node::into_val(node))
+    }
+
+    // everything after the current element as a new list, or the whole list on the ghost
+    pub fn split_after(&mut self) -> List<T> {
+        let Some(node) = self.current.clone() else {
+            self.index = 0;
+            return std::mem::take(self.list);
+        };
+        match node.next.take() {
+            Some(first) => {
+                first.prev.set(Weak::new());
+                let last = self.list.tail.replace(node).unwrap();
+                let len = self.list.len - self.index - 1;
+                self.list.len = self.index + 1;
+                let mut split = List::from_chain(first, last, len);
+                self.list.settle(&mut split);
+                split
+            }
+            None => List::new(),
+        }
+    }
+
+    // everything before the current element as a new list, or the whole list on the ghost
+    pub fn split_before(&mut self) -> List<T> {
+        let Some(node) = self.current.clone() else {
+            self.index = 0;
+            return std::mem::take(self.list);
+        };
+        match node.prev.take().upgrade() {
+            Some(last) => {
+                let first = self.list.head.replace(node).unwrap();
+                last.next.set(None);
+                let len = std::mem::take(&mut self.index);
+                self.list.len -= len;
+                let mut split = List::from_chain(first, last, len);
+                self.list.settle(&mut split);
+                split
+            }
+            None => List::new(),
+        }
+    }
+
+    // moves all of other in before the current element, or to the back on the ghost
+    pub fn splice_before(&mut self, mut other: List<T>) {
+        let len = other.len;
+        if let Some((first, last)) = self.list.absorb(&mut other) {
+            let prev = preceding(self.list, &self.current);
+            let next = self.current.clone();
+            self.list.link_chain(prev, next, first, last);
+            self.index += len;
+        }
+    }
+
+    // moves all of other in after the current element, or to the front on the ghost
+    pub fn splice_after(&mut self, mut other: List<T>) {
+        let len = other.len;
+        if let Some((first, last)) = self.list.absorb(&mut other) {
+            let prev = self.current.clone();
+            let next = following(self.list, &self.current);
+            self.list.link_chain(prev, next, first, last);
+            if self.current.is_none() {
+                self.index += len;
+            }
+        }
+    }
+}
+
 
 #[cfg(test)]
 mod tests {
-    use super::List;
+    use super::{HandleError, List, Node};
+    use std::cell::Cell;
+    use std::mem::size_of;
+    use std::panic::{self, AssertUnwindSafe};
+    use std::rc::Rc;
 
     #[test]
     fn basic_test() {
@@ -175,4 +1324,819 @@ mod tests {
         assert_eq!(&*list.peek_front().unwrap(), &2);
         assert_eq!(&*list.peek_back().unwrap(), &1);
     }
+
+    #[test]
+    fn iter_test() {
+        let mut list = List::new();
+        for i in 1..=5 {
+            list.push_back(i);
+        }
+        let vals: Vec<i32> = list.iter().map(|val| *val).collect();
+        assert_eq!(vals, vec![1, 2, 3, 4, 5]);
+        let vals: Vec<i32> = list.iter().rev().map(|val| *val).collect();
+        assert_eq!(vals, vec![5, 4, 3, 2, 1]);
+
+        // both ends meet in the middle and every node is visited once
+        let mut iter = list.iter();
+        assert_eq!(*iter.next().unwrap(), 1);
+        assert_eq!(*iter.next_back().unwrap(), 5);
+        assert_eq!(*iter.next().unwrap(), 2);
+        assert_eq!(*iter.next_back().unwrap(), 4);
+        assert_eq!(*iter.next().unwrap(), 3);
+        assert!(iter.next().is_none());
+        assert!(iter.next_back().is_none());
+
+        // the list is still there
+        assert_eq!(list.pop_front(), Some(1));
+        assert!(List::<i32>::new().iter().next().is_none());
+    }
+
+    #[test]
+    fn iter_mut_test() {
+        let mut list = List::new();
+        for i in 1..=4 {
+            list.push_back(i);
+        }
+        for mut val in list.iter_mut() {
+            *val *= 10;
+        }
+        *list.peek_front_mut().unwrap() += 1;
+        *list.peek_back_mut().unwrap() += 2;
+        let vals: Vec<i32> = list.iter().map(|val| *val).collect();
+        assert_eq!(vals, vec![11, 20, 30, 42]);
+        assert!(List::<i32>::new().peek_front_mut().is_none());
+    }
+
+    #[test]
+    fn no_borrow_panic_test() {
+        let mut list = List::new();
+        for i in 0..6 {
+            list.push_back(i);
+        }
+
+        // holding every RefMut at once, taken from both ends, never borrows a node twice
+        let mut all: Vec<_> = Vec::new();
+        let mut iter = list.iter_mut();
+        while let Some(front) = iter.next() {
+            all.push(front);
+            if let Some(back) = iter.next_back() {
+                all.push(back);
+            }
+        }
+        for val in all.iter_mut() {
+            **val += 100;
+        }
+        drop(all);
+
+        // many shared iterators and peeks at the same time are fine too
+        let refs: Vec<_> = list.iter().collect();
+        let again: Vec<_> = list.iter().rev().collect();
+        let front = list.peek_front().unwrap();
+        assert_eq!(*refs[0], 100);
+        assert_eq!(*again[0], 105);
+        assert_eq!(*front, *refs[0]);
+    }
+
+    // counts its drops, and panics in drop if told to
+    struct Counted {
+        drops: Rc<Cell<usize>>,
+        panic_on_drop: bool,
+    }
+
+    impl Drop for Counted {
+        fn drop(&mut self) {
+            self.drops.set(self.drops.get() + 1);
+            if self.panic_on_drop {
+                panic!("element drop failed");
+            }
+        }
+    }
+
+    fn counted_list(drops: &Rc<Cell<usize>>, len: usize, panic_at: Option<usize>) -> List<Counted> {
+        let mut list = List::new();
+        for i in 0..len {
+            list.push_back(Counted {
+                drops: drops.clone(),
+                panic_on_drop: panic_at == Some(i),
+            });
+        }
+        list
+    }
+
+    #[test]
+    fn drop_count_test() {
+        let drops = Rc::new(Cell::new(0));
+        let mut list = counted_list(&drops, 10, None);
+        drop(list.pop_front());
+        drop(list.pop_back());
+        assert_eq!(drops.get(), 2);
+        drop(list);
+        assert_eq!(drops.get(), 10);
+
+        // a partly consumed IntoIter frees the rest, from both ends
+        let mut iter = counted_list(&drops, 10, None).into_iter();
+        drop(iter.next());
+        drop(iter.next_back());
+        drop(iter);
+        assert_eq!(drops.get(), 20);
+    }
+
+    #[test]
+    fn panic_in_drop_test() {
+        // Drop stops popping when an element panics, the remaining nodes must still be freed
+        let drops = Rc::new(Cell::new(0));
+        let list = counted_list(&drops, 100, Some(40));
+        let result = panic::catch_unwind(AssertUnwindSafe(move || drop(list)));
+        assert!(result.is_err());
+        assert_eq!(drops.get(), 100);
+
+        // the rest is dropped as a whole chain then, which must not take one stack frame per node
+        let list = counted_list(&drops, 300_001, Some(0));
+        let result = panic::catch_unwind(AssertUnwindSafe(move || drop(list)));
+        assert!(result.is_err());
+        assert_eq!(drops.get(), 300_101);
+    }
+
+    #[test]
+    fn panic_mid_operation_test() {
+        let drops = Rc::new(Cell::new(0));
+        let mut list = counted_list(&drops, 20, None);
+
+        // a panic while holding RefMuts from iter_mut releases them, the list is intact afterwards
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            let held: Vec<_> = list.iter_mut().take(5).collect();
+            if held.len() == 5 {
+                panic!("caller failed halfway");
+            }
+        }));
+        assert!(result.is_err());
+        assert_eq!(list.iter().count(), 20);
+        assert_eq!(drops.get(), 0);
+
+        // a panic while consuming the list frees everything not yet taken
+        let result = panic::catch_unwind(AssertUnwindSafe(move || {
+            for (i, _) in list.into_iter().enumerate() {
+                if i == 7 {
+                    panic!("consumer failed halfway");
+                }
+            }
+        }));
+        assert!(result.is_err());
+        assert_eq!(drops.get(), 20);
+    }
+
+    fn values(list: &List<i32>) -> Vec<i32> {
+        list.iter().map(|val| *val).collect()
+    }
+
+    #[test]
+    fn handle_test() {
+        let mut list = List::new();
+        let one = list.push_back_handle(1);
+        let two = list.push_back_handle(2);
+        let three = list.push_back_handle(3);
+        let zero = list.push_front_handle(0);
+        assert_eq!(values(&list), vec![0, 1, 2, 3]);
+        assert_eq!(*two.get(&list).unwrap(), 2);
+
+        // removing from the middle and both ends keeps the links consistent both ways
+        assert_eq!(two.remove(&mut list), Ok(2));
+        assert_eq!(values(&list), vec![0, 1, 3]);
+        assert_eq!(zero.remove(&mut list), Ok(0));
+        assert_eq!(three.remove(&mut list), Ok(3));
+        assert_eq!(values(&list), vec![1]);
+        assert_eq!(
+            list.iter().rev().map(|val| *val).collect::<Vec<_>>(),
+            vec![1]
+        );
+
+        let four = one.insert_after(&mut list, 4).unwrap();
+        one.insert_before(&mut list, 5).unwrap();
+        four.insert_before(&mut list, 6).unwrap();
+        four.insert_after(&mut list, 7).unwrap();
+        assert_eq!(values(&list), vec![5, 1, 6, 4, 7]);
+        assert_eq!(
+            list.iter().rev().map(|val| *val).collect::<Vec<_>>(),
+            vec![7, 4, 6, 1, 5]
+        );
+
+        four.move_to_front(&mut list).unwrap();
+        one.move_to_back(&mut list).unwrap();
+        assert_eq!(values(&list), vec![4, 5, 6, 7, 1]);
+        *four.get_mut(&mut list).unwrap() = 40;
+        assert_eq!(list.pop_front(), Some(40));
+        assert_eq!(list.pop_back(), Some(1));
+        assert_eq!(values(&list), vec![5, 6, 7]);
+    }
+
+    #[test]
+    fn handle_error_test() {
+        let mut list = List::new();
+        let mut other = List::new();
+        let a = list.push_back_handle(1);
+        let b = other.push_back_handle(2);
+
+        // a removed node, whether removed by handle or popped
+        assert_eq!(a.remove(&mut list), Ok(1));
+        assert_eq!(a.remove(&mut list), Err(HandleError::Removed));
+        assert_eq!(a.get(&list).err(), Some(HandleError::Removed));
+        let c = list.push_back_handle(3);
+        list.pop_front();
+        assert_eq!(c.move_to_front(&mut list), Err(HandleError::Removed));
+
+        // another list's node leaves both lists alone
+        list.push_back(4);
+        assert_eq!(b.remove(&mut list), Err(HandleError::ForeignList));
+        assert_eq!(
+            b.insert_after(&mut list, 5).err(),
+            Some(HandleError::ForeignList)
+        );
+        assert_eq!(b.move_to_back(&mut list), Err(HandleError::ForeignList));
+        assert_eq!(values(&list), vec![4]);
+        assert_eq!(values(&other), vec![2]);
+
+        // the handle outliving its list
+        drop(other);
+        assert_eq!(b.get(&list).err(), Some(HandleError::Removed));
+    }
+
+    #[test]
+    fn cursor_test() {
+        let mut list = List::new();
+        for i in 1..=3 {
+            list.push_back(i);
+        }
+        {
+            let mut cursor = list.cursor_front();
+            assert_eq!(cursor.current().as_deref(), Some(&1));
+            assert_eq!(cursor.index(), Some(0));
+            assert_eq!(cursor.peek_prev().as_deref(), None);
+            cursor.move_prev();
+            // the ghost, between 3 and 1
+            assert!(cursor.current().is_none());
+            assert_eq!(cursor.index(), None);
+            assert_eq!(cursor.peek_next().as_deref(), Some(&1));
+            assert_eq!(cursor.peek_prev().as_deref(), Some(&3));
+            cursor.move_prev();
+            assert_eq!(cursor.current().as_deref(), Some(&3));
+            assert_eq!(cursor.index(), Some(2));
+        }
+
+        {
+            let mut cursor = list.cursor_front_mut();
+            cursor.move_next();
+            *cursor.current().unwrap() *= 10;
+            cursor.insert_before(15);
+            cursor.insert_after(25);
+            *cursor.peek_next().unwrap() += 1;
+            assert_eq!(cursor.as_cursor().peek_prev().as_deref(), Some(&15));
+        }
+        assert_eq!(values(&list), vec![1, 15, 20, 26, 3]);
+
+        // 1 [15] 20 26 3: splitting off both sides leaves just the current element
+        let (before, after) = {
+            let mut cursor = list.cursor_front_mut();
+            cursor.move_next();
+            let after = cursor.split_after();
+            (cursor.split_before(), after)
+        };
+        assert_eq!(values(&before), vec![1]);
+        assert_eq!(values(&after), vec![20, 26, 3]);
+        assert_eq!(values(&list), vec![15]);
+        assert_eq!(list.peek_back().as_deref(), Some(&15));
+
+        {
+            let mut cursor = list.cursor_back_mut();
+            cursor.splice_before(before);
+            cursor.splice_after(after);
+            assert_eq!(cursor.remove_current(), Some(15));
+            assert_eq!(cursor.current().as_deref().copied(), Some(20));
+        }
+        assert_eq!(values(&list), vec![1, 20, 26, 3]);
+        assert_eq!(
+            list.iter().rev().map(|val| *val).collect::<Vec<_>>(),
+            vec![3, 26, 20, 1]
+        );
+    }
+
+    #[test]
+    fn cursor_handle_test() {
+        // handles follow their nodes into the list they are split off to or spliced into
+        let mut list = List::new();
+        let handles: Vec<_> = (0..6).map(|i| list.push_back_handle(i)).collect();
+        let mut rest = {
+            let mut cursor = list.cursor_front_mut();
+            cursor.move_next();
+            cursor.move_next();
+            cursor.split_after()
+        };
+        assert_eq!(handles[4].remove(&mut list), Err(HandleError::ForeignList));
+        assert_eq!(handles[4].remove(&mut rest), Ok(4));
+        assert_eq!(handles[1].remove(&mut rest), Err(HandleError::ForeignList));
+
+        let mut other = List::new();
+        other.cursor_front_mut().splice_after(rest);
+        assert_eq!(handles[5].remove(&mut other), Ok(5));
+        assert_eq!(values(&other), vec![3]);
+        assert_eq!(values(&list), vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn cursor_model_test() {
+        // std's LinkedList cursors are unstable, so the model is a Vec and a position in it,
+        // where position len() is the ghost
+        let mut seed = 3u64;
+        let mut next = || {
+            seed = seed
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (seed >> 33) as i32
+        };
+        let mut list = List::new();
+        let mut model: Vec<i32> = Vec::new();
+        let mut pos = 0;
+        {
+            let mut cursor = list.cursor_front_mut();
+            for _ in 0..5000 {
+                let len = model.len();
+                let val = next() % 1000;
+                match next() % 12 {
+                    0 | 1 => {
+                        cursor.move_next();
+                        pos = if pos == len { 0 } else { pos + 1 };
+                    }
+                    2 | 3 => {
+                        cursor.move_prev();
+                        pos = if pos == 0 { len } else { pos - 1 };
+                    }
+                    4 => {
+                        cursor.insert_before(val);
+                        model.insert(pos, val);
+                        pos += 1;
+                    }
+                    5 => {
+                        cursor.insert_after(val);
+                        match pos == len {
+                            true => {
+                                model.insert(0, val);
+                                pos += 1;
+                            }
+                            false => model.insert(pos + 1, val),
+                        }
+                    }
+                    6 | 7 => {
+                        let removed = (pos < len).then(|| model.remove(pos));
+                        assert_eq!(cursor.remove_current(), removed);
+                    }
+                    8 => {
+                        let split = cursor.split_after();
+                        let expected = match pos == len {
+                            true => std::mem::take(&mut model),
+                            false => model.split_off(pos + 1),
+                        };
+                        assert_eq!(values(&split), expected);
+                        assert_eq!(split.len(), expected.len());
+                        pos = pos.min(model.len());
+                    }
+                    9 => {
+                        let split = cursor.split_before();
+                        let expected = match pos == len {
+                            true => std::mem::take(&mut model),
+                            false => model.drain(..pos).collect(),
+                        };
+                        assert_eq!(values(&split), expected);
+                        assert_eq!(split.len(), expected.len());
+                        pos = 0;
+                    }
+                    10 => {
+                        let other: Vec<i32> = (0..next() % 4).map(|i| val + i).collect();
+                        let mut spliced = List::new();
+                        other.iter().for_each(|&i| spliced.push_back(i));
+                        cursor.splice_before(spliced);
+                        let at = pos;
+                        model.splice(at..at, other.iter().copied());
+                        pos += other.len();
+                    }
+                    _ => {
+                        let other: Vec<i32> = (0..next() % 4).map(|i| val + i).collect();
+                        let mut spliced = List::new();
+                        other.iter().for_each(|&i| spliced.push_back(i));
+                        cursor.splice_after(spliced);
+                        let at = if pos == len { 0 } else { pos + 1 };
+                        model.splice(at..at, other.iter().copied());
+                        if pos == len {
+                            pos += other.len();
+                        }
+                    }
+                }
+                let len = model.len();
+                let peek_next = match pos == len {
+                    true => model.first(),
+                    false => model.get(pos + 1),
+                };
+                let peek_prev = match pos {
+                    _ if pos == len => model.last(),
+                    0 => None,
+                    _ => model.get(pos - 1),
+                };
+                assert_eq!(cursor.current().as_deref(), model.get(pos));
+                assert_eq!(cursor.index(), (pos < len).then_some(pos));
+                assert_eq!(cursor.peek_next().as_deref(), peek_next);
+                assert_eq!(cursor.peek_prev().as_deref(), peek_prev);
+            }
+        }
+        assert_eq!(values(&list), model);
+        assert_eq!(list.len(), model.len());
+        assert_eq!(
+            list.iter().rev().map(|val| *val).collect::<Vec<_>>(),
+            model.iter().rev().copied().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn node_size_test() {
+        // the element's borrow flag, the element in its Option, the two links and the list's token,
+        // Rc's counts come on top
+        assert_eq!(size_of::<Node<usize>>(), 6 * size_of::<usize>());
+        assert_eq!(size_of::<Node<Box<u8>>>(), 5 * size_of::<usize>());
+    }
+
+    #[test]
+    fn leaked_iter_cursor_test() {
+        // iterators that are never dropped keep their nodes alive, but every element can still be taken and freed
+        let drops = Rc::new(Cell::new(0));
+        let mut list = counted_list(&drops, 6, None);
+        let mut iter = list.iter();
+        let _ = iter.next();
+        let _ = iter.next_back();
+        std::mem::forget(iter);
+        let mut iter = list.iter_mut();
+        let _ = iter.next();
+        std::mem::forget(iter);
+        let mut cursor = list.cursor_front();
+        cursor.move_next();
+        std::mem::forget(cursor);
+        let mut cursor = list.cursor_back_mut();
+        cursor.move_prev();
+        std::mem::forget(cursor);
+
+        let _ = list.pop_front();
+        let _ = list.pop_back();
+        let mut cursor = list.cursor_front_mut();
+        std::mem::forget(cursor.remove_current());
+        std::mem::forget(cursor);
+        assert_eq!(drops.get(), 2);
+        drop(list);
+        assert_eq!(drops.get(), 5);
+    }
+
+    #[test]
+    fn borrow_conflict_test() {
+        let mut list = List::new();
+        list.push_back(1);
+        list.push_back(2);
+        // a leaked Ref keeps the head element borrowed for good
+        let (front, line) = (list.peek_front(), line!());
+        std::mem::forget(front);
+
+        // only borrowing that element again conflicts
+        assert_eq!(*list.try_peek_front().unwrap().unwrap(), 1);
+        let err = list.try_peek_front_mut().unwrap_err();
+        assert_eq!((err.op, err.index), ("peek_front_mut", 0));
+        assert!(err
+            .to_string()
+            .starts_with("peek_front_mut: the element at index 0 is already borrowed"));
+
+        #[cfg(feature = "borrow-tracking")]
+        {
+            let at = err.borrowed_at.unwrap();
+            assert_eq!((at.file(), at.line()), (file!(), line));
+            assert!(err.to_string().contains(&format!("{}:{}", file!(), line)));
+        }
+        #[cfg(not(feature = "borrow-tracking"))]
+        {
+            let _ = line;
+            assert_eq!(err.borrowed_at, None);
+        }
+
+        // relinking never touches the element, so pushes and pops around it go through
+        list.push_front(0);
+        list.push_back(3);
+        *list.try_peek_back_mut().unwrap().unwrap() += 10;
+        assert_eq!(list.pop_front(), Some(0));
+        assert_eq!(list.pop_back(), Some(13));
+        assert_eq!(list.pop_back(), Some(2));
+        // even the borrowed node itself leaves, taking the leaked borrow with it
+        assert_eq!(list.pop_front(), Some(1));
+        assert_eq!(list.pop_front(), None);
+
+        let mut other = List::new();
+        other.push_back(5);
+        std::mem::forget(other.peek_back());
+        assert!(other.try_peek_back_mut().is_err());
+        assert_eq!(other.iter().map(|val| *val).collect::<Vec<_>>(), vec![5]);
+        // and dropping it does not panic either
+        drop(other);
+    }
+
+    #[test]
+    fn handle_borrow_conflict_test() {
+        let mut list = List::new();
+        let handles: Vec<_> = (0..4).map(|i| list.push_back_handle(i)).collect();
+        std::mem::forget(handles[2].get(&list).unwrap());
+        std::mem::forget(handles[1].get_mut(&mut list).unwrap());
+
+        // a shared borrow only conflicts with get_mut, an exclusive one with both
+        assert_eq!(*handles[2].try_get(&list).unwrap(), 2);
+        let err = match handles[2].try_get_mut(&mut list) {
+            Err(HandleError::Borrowed(err)) => err,
+            _ => panic!("get_mut of a borrowed element went through"),
+        };
+        assert_eq!((err.op, err.index), ("get_mut", 2));
+        let err = handles[1].try_get(&list).err().unwrap();
+        assert!(err
+            .to_string()
+            .starts_with("get: the element at index 1 is already borrowed"));
+        *handles[3].try_get_mut(&mut list).unwrap() += 10;
+        assert_eq!(*handles[3].try_get(&list).unwrap(), 13);
+
+        // the other errors come first
+        let mut other = List::new();
+        other.push_back(0);
+        assert_eq!(
+            handles[1].try_get(&other).err(),
+            Some(HandleError::ForeignList)
+        );
+    }
+
+    #[test]
+    fn cursor_borrow_conflict_test() {
+        let mut list = List::new();
+        for i in 0..4 {
+            list.push_back(i);
+        }
+        std::mem::forget(list.peek_back_mut());
+
+        let mut cursor = list.cursor_back();
+        let err = cursor.try_current().err().unwrap();
+        assert_eq!((err.op, err.index), ("current", 3));
+        cursor.move_prev();
+        assert_eq!(cursor.try_current().unwrap().as_deref(), Some(&2));
+        assert_eq!(cursor.try_peek_prev().unwrap().as_deref(), Some(&1));
+        let err = cursor.try_peek_next().err().unwrap();
+        assert_eq!((err.op, err.index), ("peek_next", 3));
+        cursor.move_next();
+        cursor.move_next();
+        // the ghost
+        assert!(cursor.try_current().unwrap().is_none());
+        assert_eq!(cursor.try_peek_next().unwrap().as_deref(), Some(&0));
+        let err = cursor.try_peek_prev().err().unwrap();
+        assert_eq!((err.op, err.index), ("peek_prev", 3));
+    }
+
+    #[test]
+    fn cursor_mut_borrow_conflict_test() {
+        let mut list = List::new();
+        for i in 0..4 {
+            list.push_back(i);
+        }
+        // a leaked shared borrow of the head, which none of the cursor's RefMuts may overlap
+        std::mem::forget(list.peek_front());
+
+        let mut cursor = list.cursor_front_mut();
+        let err = cursor.try_current().err().unwrap();
+        assert_eq!((err.op, err.index), ("current_mut", 0));
+        cursor.move_next();
+        *cursor.try_current().unwrap().unwrap() += 10;
+        *cursor.try_peek_next().unwrap().unwrap() += 20;
+        let err = cursor.try_peek_prev().err().unwrap();
+        assert_eq!((err.op, err.index), ("peek_prev_mut", 0));
+        cursor.move_prev();
+        cursor.move_prev();
+        // the ghost
+        assert!(cursor.try_current().unwrap().is_none());
+        *cursor.try_peek_prev().unwrap().unwrap() += 30;
+        let err = cursor.try_peek_next().err().unwrap();
+        assert_eq!((err.op, err.index), ("peek_next_mut", 0));
+        assert_eq!(values(&list), vec![0, 11, 22, 33]);
+    }
+
+    #[test]
+    fn iter_borrow_conflict_test() {
+        let mut list = List::new();
+        let handles: Vec<_> = (0..5).map(|i| list.push_back_handle(i)).collect();
+        std::mem::forget(handles[1].get_mut(&mut list).unwrap());
+        std::mem::forget(handles[3].get(&list).unwrap());
+
+        // iter conflicts only with the exclusive borrow, and carries on past it
+        let mut iter = list.iter();
+        assert_eq!(iter.try_next().unwrap().as_deref().ok(), Some(&0));
+        let err = iter.try_next().unwrap().err().unwrap();
+        assert_eq!((err.op, err.index), ("next", 1));
+        assert_eq!(iter.try_next_back().unwrap().as_deref().ok(), Some(&4));
+        assert_eq!(iter.try_next_back().unwrap().as_deref().ok(), Some(&3));
+        assert_eq!(iter.try_next().unwrap().as_deref().ok(), Some(&2));
+        assert!(iter.try_next().is_none());
+
+        // iter_mut conflicts with both
+        let mut iter = list.iter_mut();
+        let errs: Vec<_> = std::iter::from_fn(|| iter.try_next_back())
+            .filter_map(Result::err)
+            .map(|err| (err.op, err.index))
+            .collect();
+        assert_eq!(errs, vec![("next_back_mut", 3), ("next_back_mut", 1)]);
+        let mut iter = list.iter_mut();
+        let mut ok = Vec::new();
+        let mut errs = Vec::new();
+        while let Some(next) = iter.try_next() {
+            match next {
+                Ok(mut val) => {
+                    *val += 10;
+                    ok.push(val);
+                }
+                Err(err) => errs.push((err.op, err.index)),
+            }
+        }
+        drop(ok);
+        assert_eq!(errs, vec![("next_mut", 1), ("next_mut", 3)]);
+        assert_eq!(*handles[4].get(&list).unwrap(), 14);
+    }
+
+    #[test]
+    fn append_test() {
+        let mut list = List::new();
+        let mut other = List::new();
+        list.append(&mut other);
+        assert!(list.is_empty());
+
+        let one = list.push_back_handle(1);
+        let two = other.push_back_handle(2);
+        other.push_back(3);
+        list.append(&mut other);
+        assert_eq!(values(&list), vec![1, 2, 3]);
+        assert_eq!((list.len(), other.len()), (3, 0));
+        assert_eq!(
+            list.iter().rev().map(|val| *val).collect::<Vec<_>>(),
+            vec![3, 2, 1]
+        );
+
+        // handles follow their node into the list it was appended to, even across several appends
+        assert_eq!(*two.get(&list).unwrap(), 2);
+        assert_eq!(two.get(&other).err(), Some(HandleError::ForeignList));
+        let zero = other.push_back_handle(0);
+        let mut third = List::new();
+        third.push_back(-1);
+        third.append(&mut list);
+        third.prepend_list(&mut other);
+        assert_eq!(values(&third), vec![0, -1, 1, 2, 3]);
+        assert_eq!(third.len(), 5);
+        two.move_to_front(&mut third).unwrap();
+        assert_eq!(one.remove(&mut third), Ok(1));
+        assert_eq!(*zero.get(&third).unwrap(), 0);
+        assert_eq!(values(&third), vec![2, 0, -1, 3]);
+
+        // the emptied lists work as new ones
+        assert_eq!(one.get(&list).err(), Some(HandleError::Removed));
+        assert_eq!(zero.get(&list).err(), Some(HandleError::ForeignList));
+        let four = list.push_back_handle(4);
+        third.prepend_list(&mut list);
+        assert_eq!(four.remove(&mut third), Ok(4));
+        assert_eq!(third.len(), 4);
+    }
+
+    #[cfg(feature = "borrow-tracking")]
+    #[test]
+    fn borrow_log_move_test() {
+        // the location of a leaked borrow goes with its node through appends, splits and splices
+        let mut list = List::new();
+        let mut other = List::new();
+        for i in 0..4 {
+            list.push_back(i);
+            other.push_back(i + 10);
+        }
+        let (back, line) = (other.peek_back(), line!());
+        std::mem::forget(back);
+        let borrowed = |list: &mut List<i32>| {
+            let err = list.try_peek_back_mut().unwrap_err();
+            (err.index, err.borrowed_at.map(|at| at.line()))
+        };
+
+        list.append(&mut other);
+        assert_eq!(borrowed(&mut list), (7, Some(line)));
+        // the longer part is split off, and takes the log along
+        let mut split = list.split_off(1);
+        assert_eq!(borrowed(&mut split), (6, Some(line)));
+        // the shorter part is split off, and its entries are moved one by one
+        let mut back = split.split_off(5);
+        assert_eq!(borrowed(&mut back), (1, Some(line)));
+        let mut cursor = back.cursor_front_mut();
+        let mut front = cursor.split_after();
+        assert_eq!(borrowed(&mut front), (0, Some(line)));
+
+        let mut target = List::new();
+        target.push_back(0);
+        target.cursor_front_mut().splice_after(front);
+        assert_eq!(borrowed(&mut target), (1, Some(line)));
+        target.prepend_list(&mut split);
+        assert_eq!(borrowed(&mut target), (6, Some(line)));
+    }
+
+    #[test]
+    fn split_handle_test() {
+        // nodes appended from another list are still tagged with its token, which forwards to ours.
+        // splitting off the longer part hands our token over to it, so those follow without being retagged
+        let mut list = List::new();
+        let mut other = List::new();
+        let mut handles = vec![list.push_back_handle(0), list.push_back_handle(1)];
+        handles.extend((2..10).map(|i| other.push_back_handle(i)));
+        list.append(&mut other);
+        let mut split = list.split_off(1);
+        assert_eq!((list.len(), split.len()), (1, 9));
+        assert_eq!(*handles[0].get(&list).unwrap(), 0);
+        assert_eq!(handles[0].get(&split).err(), Some(HandleError::ForeignList));
+        for (i, handle) in handles.iter().enumerate().skip(1) {
+            assert_eq!(*handle.get(&split).unwrap(), i as i32);
+            assert_eq!(handle.get(&list).err(), Some(HandleError::ForeignList));
+        }
+
+        // and the shorter part of a split by cursor is retagged
+        let mut cursor = split.cursor_back_mut();
+        cursor.move_prev();
+        let mut rest = cursor.split_before();
+        assert_eq!((rest.len(), split.len()), (7, 2));
+        assert_eq!(handles[9].remove(&mut split), Ok(9));
+        assert_eq!(handles[5].move_to_front(&mut rest), Ok(()));
+        assert_eq!(handles[8].get(&rest).err(), Some(HandleError::ForeignList));
+        assert_eq!(values(&rest), vec![5, 1, 2, 3, 4, 6, 7]);
+    }
+
+    #[test]
+    fn split_rotate_test() {
+        // split_off, rotate and reverse against a Vec, with a handle on every element
+        let mut list = List::new();
+        let mut model: Vec<i32> = Vec::new();
+        let mut handles = Vec::new();
+        for i in 0..40 {
+            handles.push(list.push_back_handle(i));
+            model.push(i);
+        }
+        for step in 0..200usize {
+            let len = model.len();
+            match step % 5 {
+                0 => {
+                    let n = step * 7 % (len + 1);
+                    list.rotate_left(n);
+                    model.rotate_left(n);
+                }
+                1 => {
+                    let n = step * 3 % (len + 1);
+                    list.rotate_right(n);
+                    model.rotate_right(n);
+                }
+                2 => {
+                    list.reverse();
+                    model.reverse();
+                }
+                _ => {
+                    // split off a part that is sometimes the shorter and sometimes the longer one,
+                    // check it and its handles, then put it back at either end
+                    let at = step * 11 % (len + 1);
+                    let mut split = list.split_off(at);
+                    let split_model = model.split_off(at);
+                    assert_eq!(values(&split), split_model);
+                    assert_eq!((list.len(), split.len()), (at, len - at));
+                    for (i, handle) in handles.iter().enumerate() {
+                        let (owner, foreign) = match split_model.contains(&(i as i32)) {
+                            true => (&split, &list),
+                            false => (&list, &split),
+                        };
+                        assert_eq!(*handle.get(owner).unwrap(), i as i32);
+                        assert_eq!(handle.get(foreign).err(), Some(HandleError::ForeignList));
+                    }
+                    match step % 2 {
+                        0 => {
+                            list.append(&mut split);
+                            model.extend(split_model);
+                        }
+                        _ => {
+                            list.prepend_list(&mut split);
+                            model.splice(0..0, split_model);
+                        }
+                    }
+                }
+            }
+            assert_eq!(values(&list), model);
+            assert_eq!(
+                list.iter().rev().map(|val| *val).collect::<Vec<_>>(),
+                model.iter().rev().copied().collect::<Vec<_>>()
+            );
+        }
+        for (i, handle) in handles.iter().enumerate() {
+            assert_eq!(*handle.get(&list).unwrap(), i as i32);
+        }
+        let mut empty = List::<i32>::new();
+        assert!(empty.split_off(0).is_empty());
+        empty.rotate_left(0);
+        empty.reverse();
+    }
 }
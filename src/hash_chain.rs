@@ -0,0 +1,343 @@
+use crate::serialize::Element;
+use crate::sha256::{sha256, Digest, Sha256};
+use crate::third::{self, List};
+use std::error::Error;
+use std::fmt;
+
+// a tamper-evident, append-only log on top of third::List
+//
+// every entry stores, next to its event, a digest of the event and of the digest of the entry below it:
+//
+//   value_hash = sha256(0x00 || event encoded as by serialize::Element)
+//   digest     = sha256(0x01 || value_hash || digest of the tail)
+//
+// the empty chain has the all-zero digest. since each digest covers the whole history below it, the head's
+// digest pins down every event ever appended: publish it, and nobody can change an old event without
+// either breaking verify() or producing a different head digest.
+// indices count in append order, the first event appended is index 0 and the head is len() - 1
+pub struct HashChain<T> {
+    entries: List<Entry<T>>,
+    len: usize,
+}
+
+struct Entry<T> {
+    val: T,
+    digest: Digest,
+}
+
+pub const EMPTY_DIGEST: Digest = [0; 32];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TamperError {
+    // the oldest entry whose stored digest does not match its event and tail
+    pub index: usize,
+}
+
+impl fmt::Display for TamperError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "entry {} does not match its digest", self.index)
+    }
+}
+
+impl Error for TamperError {}
+
+pub fn value_hash<T: Element>(val: &T) -> Digest {
+    let mut bytes = vec![0x00];
+    val.encode(&mut bytes);
+    sha256(&bytes)
+}
+
+fn link_digest(value_hash: &Digest, tail: &Digest) -> Digest {
+    let mut hasher = Sha256::new();
+    hasher.update(&[0x01]);
+    hasher.update(value_hash);
+    hasher.update(tail);
+    hasher.finish()
+}
+
+impl<T> HashChain<T> {
+    pub fn new() -> Self {
+        HashChain {
+            entries: List::new(),
+            len: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    // the digest of the whole chain
+    pub fn digest(&self) -> Digest {
+        self.entries
+            .head()
+            .map_or(EMPTY_DIGEST, |entry| entry.digest)
+    }
+
+    // the newest event
+    pub fn head(&self) -> Option<&T> {
+        self.entries.head().map(|entry| &entry.val)
+    }
+
+    // the chain before the newest event, sharing all its entries
+    pub fn tail(&self) -> HashChain<T> {
+        HashChain {
+            entries: self.entries.clone().tail(),
+            len: self.len.saturating_sub(1),
+        }
+    }
+
+    // newest first, like third::List
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.entries.iter().map(|entry| &entry.val)
+    }
+
+    // the stored digests next to their events, newest first
+    pub fn entries(&self) -> impl Iterator<Item = (&T, &Digest)> {
+        self.entries.iter().map(|entry| (&entry.val, &entry.digest))
+    }
+}
+
+impl<T: Element> HashChain<T> {
+    pub fn append(&self, val: T) -> HashChain<T> {
+        let digest = link_digest(&value_hash(&val), &self.digest());
+        HashChain {
+            entries: self.entries.clone().prepend(Entry { val, digest }),
+            len: self.len + 1,
+        }
+    }
+
+    // rebuilds a chain from storage, oldest event first, keeping the digests as they were stored.
+    // nothing is checked here, that is what verify() is for
+    pub fn from_stored<I>(entries: I) -> HashChain<T>
+    where
+        I: IntoIterator<Item = (T, Digest)>,
+    {
+        entries
+            .into_iter()
+            .fold(HashChain::new(), |mut chain, (val, digest)| HashChain {
+                entries: chain.entries.prepend(Entry { val, digest }),
+                len: chain.len + 1,
+            })
+    }
+
+    // recomputes every digest from the oldest entry up and returns the head digest if all of them match.
+    // this proves the chain is consistent, not that it is the chain you expect: compare the result
+    // with a head digest obtained from somewhere you trust
+    pub fn verify(&self) -> Result<Digest, TamperError> {
+        let entries: Vec<&Entry<T>> = self.entries.iter().collect();
+        let mut digest = EMPTY_DIGEST;
+        for (i, entry) in entries.into_iter().rev().enumerate() {
+            digest = link_digest(&value_hash(&entry.val), &digest);
+            if digest != entry.digest {
+                return Err(TamperError { index: i });
+            }
+        }
+        Ok(digest)
+    }
+
+    // what someone holding only the head digest needs to check that the event at index is in the chain:
+    // the digest below the event and the value hashes of every event appended after it
+    pub fn proof_of_inclusion(&self, index: usize) -> Option<InclusionProof> {
+        if index >= self.len {
+            return None;
+        }
+        // the entries from the head down to index, then the one below it if there is one
+        let entries: Vec<&Entry<T>> = self.entries.iter().take(self.len - index + 1).collect();
+        let at = self.len - index - 1;
+        Some(InclusionProof {
+            index,
+            below: entries
+                .get(at + 1)
+                .map_or(EMPTY_DIGEST, |below| below.digest),
+            newer: entries[..at]
+                .iter()
+                .rev()
+                .map(|entry| value_hash(&entry.val))
+                .collect(),
+        })
+    }
+}
+
+impl<T> HashChain<T> {
+    // number of events both chains agree on, counted from the first one appended.
+    // a digest at index i covers events 0..=i, so the first equal digest met walking down from the top
+    // marks the end of the common history, and identical entries stop the walk right away
+    pub fn common_history_len(a: &HashChain<T>, b: &HashChain<T>) -> usize {
+        let mut len = a.len.min(b.len);
+        let mut x = a.entries.iter().skip(a.len - len);
+        let mut y = b.entries.iter().skip(b.len - len);
+        while let (Some(p), Some(q)) = (x.next(), y.next()) {
+            if std::ptr::eq(p, q) || p.digest == q.digest {
+                return len;
+            }
+            len -= 1;
+        }
+        0
+    }
+
+    // index of the first event where the chains differ, None if they are the same chain.
+    // when one chain extends the other, that is the first event only the longer one has
+    pub fn first_divergence(a: &HashChain<T>, b: &HashChain<T>) -> Option<usize> {
+        let common = HashChain::common_history_len(a, b);
+        match common == a.len && common == b.len {
+            true => None,
+            false => Some(common),
+        }
+    }
+}
+
+impl<T> Default for HashChain<T> {
+    fn default() -> Self {
+        HashChain::new()
+    }
+}
+
+impl<T> Clone for HashChain<T> {
+    fn clone(&self) -> Self {
+        HashChain {
+            entries: self.entries.clone(),
+            len: self.len,
+        }
+    }
+}
+
+impl<T> From<&HashChain<T>> for third::List<T>
+where
+    T: Clone,
+{
+    fn from(chain: &HashChain<T>) -> Self {
+        let vals: Vec<&T> = chain.iter().collect();
+        vals.into_iter()
+            .rev()
+            .fold(List::new(), |mut list, val| list.prepend(val.clone()))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InclusionProof {
+    pub index: usize,
+    // digest of the chain right before the event
+    pub below: Digest,
+    // value hashes of the events appended after it, oldest first
+    pub newer: Vec<Digest>,
+}
+
+impl InclusionProof {
+    // true if val, at this proof's index, leads up to head
+    pub fn verify<T: Element>(&self, val: &T, head: &Digest) -> bool {
+        let mut digest = link_digest(&value_hash(val), &self.below);
+        for hash in &self.newer {
+            digest = link_digest(hash, &digest);
+        }
+        digest == *head
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{HashChain, TamperError, EMPTY_DIGEST};
+    use crate::sha256::Digest;
+
+    fn log(events: &[&str]) -> HashChain<String> {
+        events
+            .iter()
+            .fold(HashChain::new(), |chain, e| chain.append(e.to_string()))
+    }
+
+    #[test]
+    fn verify_test() {
+        let empty = HashChain::<String>::new();
+        assert_eq!(empty.verify(), Ok(EMPTY_DIGEST));
+
+        let chain = log(&["login alice", "grant admin", "logout alice"]);
+        assert_eq!(chain.len(), 3);
+        assert_eq!(chain.head().map(String::as_str), Some("logout alice"));
+        assert_eq!(chain.verify(), Ok(chain.digest()));
+        assert_ne!(chain.digest(), chain.tail().digest());
+
+        // the same events give the same digest, a different order does not
+        assert_eq!(
+            chain.digest(),
+            log(&["login alice", "grant admin", "logout alice"]).digest()
+        );
+        assert_ne!(
+            chain.digest(),
+            log(&["grant admin", "login alice", "logout alice"]).digest()
+        );
+    }
+
+    #[test]
+    fn tamper_test() {
+        let chain = log(&["a", "b", "c", "d"]);
+        let stored: Vec<(String, Digest)> = chain
+            .entries()
+            .map(|(val, digest)| (val.clone(), *digest))
+            .collect::<Vec<_>>()
+            .into_iter()
+            .rev()
+            .collect();
+
+        let reloaded = HashChain::from_stored(stored.clone());
+        assert_eq!(reloaded.verify(), Ok(chain.digest()));
+
+        // an edited event no longer matches its stored digest
+        let mut edited = stored.clone();
+        edited[1].0 = "B".to_string();
+        assert_eq!(
+            HashChain::from_stored(edited).verify(),
+            Err(TamperError { index: 1 })
+        );
+
+        // rewriting the digests as well is consistent, but gives another head digest
+        let forged = log(&["a", "B", "c", "d"]);
+        assert_eq!(forged.verify(), Ok(forged.digest()));
+        assert_ne!(forged.digest(), chain.digest());
+
+        // dropping an entry breaks the link of the one above it
+        let mut dropped = stored;
+        dropped.remove(2);
+        assert_eq!(
+            HashChain::from_stored(dropped).verify(),
+            Err(TamperError { index: 2 })
+        );
+    }
+
+    #[test]
+    fn inclusion_test() {
+        let events = ["a", "b", "c", "d", "e"];
+        let chain = log(&events);
+        let head = chain.digest();
+        for (i, event) in events.iter().enumerate() {
+            let proof = chain.proof_of_inclusion(i).unwrap();
+            assert_eq!(proof.newer.len(), events.len() - i - 1);
+            assert!(proof.verify(&event.to_string(), &head));
+            assert!(!proof.verify(&"x".to_string(), &head));
+            assert!(!proof.verify(&event.to_string(), &chain.tail().digest()));
+        }
+        assert!(chain.proof_of_inclusion(5).is_none());
+    }
+
+    #[test]
+    fn divergence_test() {
+        let base = log(&["a", "b", "c"]);
+        let left = base.append("d".to_string()).append("e".to_string());
+        let right = base.append("x".to_string());
+
+        assert_eq!(HashChain::common_history_len(&left, &right), 3);
+        assert_eq!(HashChain::first_divergence(&left, &right), Some(3));
+        assert_eq!(HashChain::first_divergence(&left, &left.clone()), None);
+        assert_eq!(HashChain::first_divergence(&base, &left), Some(3));
+
+        // independently built chains with the same events agree too, through their digests
+        let rebuilt = log(&["a", "b", "c", "d", "e"]);
+        assert_eq!(HashChain::first_divergence(&left, &rebuilt), None);
+        let other = log(&["z", "b", "c"]);
+        assert_eq!(HashChain::first_divergence(&other, &base), Some(0));
+        assert_eq!(HashChain::common_history_len(&HashChain::new(), &base), 0);
+    }
+}
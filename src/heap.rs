@@ -0,0 +1,388 @@
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::iter::FromIterator;
+use std::rc::Rc;
+
+// a persistent leftist heap, sharing nodes through Rc like third::List
+//
+// the smallest element sits at the root, and every node's left child has a rank (length of the rightmost path)
+// at least that of its right child. so the right spine of a heap of n elements is at most log(n + 1) long,
+// and merging only walks the two right spines: their nodes are rebuilt, everything hanging off to the left is
+// shared with the old versions, which stay valid. a spine node that no other version holds gives its value
+// up to the rebuilt one, only a shared one has to have its value cloned
+pub struct PersistentHeap<T> {
+    root: Link<T>,
+}
+
+type Link<T> = Option<Rc<Node<T>>>;
+
+struct Node<T> {
+    val: T,
+    rank: usize,
+    len: usize,
+    left: Link<T>,
+    right: Link<T>,
+}
+
+fn rank<T>(link: &Link<T>) -> usize {
+    link.as_ref().map_or(0, |node| node.rank)
+}
+
+fn len<T>(link: &Link<T>) -> usize {
+    link.as_ref().map_or(0, |node| node.len)
+}
+
+// a node over two subtrees, the higher ranked one going left
+fn make<T>(val: T, a: Link<T>, b: Link<T>) -> Link<T> {
+    let (left, right) = if rank(&a) >= rank(&b) { (a, b) } else { (b, a) };
+    Some(Rc::new(Node {
+        val,
+        rank: rank(&right) + 1,
+        len: len(&left) + len(&right) + 1,
+        left,
+        right,
+    }))
+}
+
+// the value and children of a node being rebuilt: moved out if nothing else holds the node, cloned otherwise
+fn open<T: Clone>(node: Rc<Node<T>>) -> (T, Link<T>, Link<T>) {
+    match Rc::try_unwrap(node) {
+        Ok(node) => (node.val, node.left, node.right),
+        Err(node) => (node.val.clone(), node.left.clone(), node.right.clone()),
+    }
+}
+
+// the same for heaps that were just built from owned values, where no node can be shared yet
+fn open_unique<T>(node: Rc<Node<T>>) -> (T, Link<T>, Link<T>) {
+    match Rc::try_unwrap(node) {
+        Ok(node) => (node.val, node.left, node.right),
+        Err(_) => unreachable!("a freshly built heap node is shared"),
+    }
+}
+
+type Open<T> = fn(Rc<Node<T>>) -> (T, Link<T>, Link<T>);
+
+// merging down the right spines, written as a loop: the smaller root of the two is kept with its left subtree,
+// and its right subtree is merged with the other heap. once a side runs out, the rebuilt spine is put back
+// together from the bottom up
+fn merge<T: Ord>(mut a: Link<T>, mut b: Link<T>, open: Open<T>) -> Link<T> {
+    let mut spine = Vec::new();
+    let mut merged = loop {
+        match (a, b) {
+            (None, rest) | (rest, None) => break rest,
+            (Some(x), Some(y)) => {
+                let (small, other) = if x.val <= y.val { (x, y) } else { (y, x) };
+                let (val, left, right) = open(small);
+                a = right;
+                b = Some(other);
+                spine.push((val, left));
+            }
+        }
+    };
+    while let Some((val, left)) = spine.pop() {
+        merged = make(val, left, merged);
+    }
+    merged
+}
+
+impl<T> PersistentHeap<T> {
+    pub fn new() -> Self {
+        PersistentHeap { root: None }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.root.is_none()
+    }
+
+    pub fn len(&self) -> usize {
+        len(&self.root)
+    }
+
+    pub fn peek_min(&self) -> Option<&T> {
+        self.root.as_ref().map(|node| &node.val)
+    }
+}
+
+// the versions these return from share every node with self, which keeps its own, so the rebuilt spine
+// is always made of clones. push and pop below change a heap in place instead, and only clone what
+// some other version still holds
+impl<T: Ord + Clone> PersistentHeap<T> {
+    pub fn insert(&self, val: T) -> PersistentHeap<T> {
+        let single = make(val, None, None);
+        PersistentHeap {
+            root: merge(self.root.clone(), single, open),
+        }
+    }
+
+    // the minimum together with the heap without it, or None if this one is empty
+    pub fn pop_min(&self) -> Option<(&T, PersistentHeap<T>)> {
+        self.root.as_ref().map(|node| {
+            let rest = PersistentHeap {
+                root: merge(node.left.clone(), node.right.clone(), open),
+            };
+            (&node.val, rest)
+        })
+    }
+
+    pub fn merge(&self, other: &PersistentHeap<T>) -> PersistentHeap<T> {
+        PersistentHeap {
+            root: merge(self.root.clone(), other.root.clone(), open),
+        }
+    }
+
+    pub fn push(&mut self, val: T) {
+        let single = make(val, None, None);
+        self.root = merge(self.root.take(), single, open);
+    }
+
+    // takes out the minimum
+    pub fn pop(&mut self) -> Option<T> {
+        let (val, left, right) = open(self.root.take()?);
+        self.root = merge(left, right, open);
+        Some(val)
+    }
+}
+
+impl<T: Ord> PersistentHeap<T> {
+    // ascending order, without touching the heap: the iterator keeps the roots of the subtrees
+    // it has not entered yet in a std BinaryHeap, and the smallest of them is always the next element
+    pub fn iter(&self) -> Iter<'_, T> {
+        let mut frontier = BinaryHeap::new();
+        if let Some(root) = &self.root {
+            frontier.push(ByVal(root));
+        }
+        Iter { frontier }
+    }
+}
+
+impl<T> Default for PersistentHeap<T> {
+    fn default() -> Self {
+        PersistentHeap::new()
+    }
+}
+
+impl<T> Clone for PersistentHeap<T> {
+    fn clone(&self) -> Self {
+        PersistentHeap {
+            root: self.root.clone(),
+        }
+    }
+}
+
+// merging the elements as singletons pairwise, round after round, builds the heap in O(n)
+impl<T: Ord> FromIterator<T> for PersistentHeap<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut heaps: Vec<Link<T>> = iter.into_iter().map(|val| make(val, None, None)).collect();
+        while heaps.len() > 1 {
+            let mut next = Vec::with_capacity(heaps.len() / 2 + 1);
+            let mut pairs = heaps.into_iter();
+            while let Some(a) = pairs.next() {
+                next.push(match pairs.next() {
+                    Some(b) => merge(a, b, open_unique),
+                    None => a,
+                });
+            }
+            heaps = next;
+        }
+        PersistentHeap {
+            root: heaps.pop().flatten(),
+        }
+    }
+}
+
+// a node has two children, so instead of third's single chain we keep a stack of the subtrees still to free.
+// a shared subtree belongs to another version too and is left alone
+impl<T> Drop for PersistentHeap<T> {
+    fn drop(&mut self) {
+        let mut stack: Vec<Rc<Node<T>>> = self.root.take().into_iter().collect();
+        while let Some(node) = stack.pop() {
+            if let Ok(mut node) = Rc::try_unwrap(node) {
+                stack.extend(node.left.take());
+                stack.extend(node.right.take());
+            }
+        }
+    }
+}
+
+// a node ordered by its value, reversed so std's max-heap pops the smallest
+struct ByVal<'a, T>(&'a Rc<Node<T>>);
+
+impl<T: Ord> PartialEq for ByVal<'_, T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.val == other.0.val
+    }
+}
+
+impl<T: Ord> Eq for ByVal<'_, T> {}
+
+impl<T: Ord> PartialOrd for ByVal<'_, T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T: Ord> Ord for ByVal<'_, T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.0.val.cmp(&self.0.val)
+    }
+}
+
+pub struct Iter<'a, T> {
+    frontier: BinaryHeap<ByVal<'a, T>>,
+}
+
+impl<'a, T: Ord> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+    fn next(&mut self) -> Option<Self::Item> {
+        let ByVal(node) = self.frontier.pop()?;
+        for child in [&node.left, &node.right].into_iter().flatten() {
+            self.frontier.push(ByVal(child));
+        }
+        Some(&node.val)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PersistentHeap;
+    use std::cell::Cell;
+
+    #[test]
+    fn basic_test() {
+        let heap = PersistentHeap::new();
+        assert_eq!(heap.peek_min(), None);
+        assert!(heap.pop_min().is_none());
+
+        let heap = heap.insert(5).insert(1).insert(8).insert(3);
+        assert_eq!(heap.len(), 4);
+        assert_eq!(heap.peek_min(), Some(&1));
+
+        let (min, rest) = heap.pop_min().unwrap();
+        assert_eq!(*min, 1);
+        assert_eq!(rest.peek_min(), Some(&3));
+        let (min, rest) = rest.pop_min().unwrap();
+        assert_eq!(*min, 3);
+        assert_eq!(rest.iter().collect::<Vec<_>>(), vec![&5, &8]);
+
+        // the old version is untouched
+        assert_eq!(heap.iter().collect::<Vec<_>>(), vec![&1, &3, &5, &8]);
+        assert_eq!(heap.len(), 4);
+    }
+
+    #[test]
+    fn merge_test() {
+        let odd: PersistentHeap<i32> = (0..50).map(|i| i * 2 + 1).collect();
+        let even: PersistentHeap<i32> = (0..50).rev().map(|i| i * 2).collect();
+        let all = odd.merge(&even);
+        assert_eq!(all.len(), 100);
+        assert_eq!(
+            all.iter().cloned().collect::<Vec<_>>(),
+            (0..100).collect::<Vec<_>>()
+        );
+
+        // duplicates are kept
+        let twice = all.merge(&all);
+        assert_eq!(twice.len(), 200);
+        assert_eq!(
+            twice.iter().take(4).collect::<Vec<_>>(),
+            vec![&0, &0, &1, &1]
+        );
+    }
+
+    #[test]
+    fn model_test() {
+        // pseudo-random operations on several versions against a sorted Vec
+        let mut seed = 7u64;
+        let mut next = || {
+            seed = seed
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (seed >> 33) as u32
+        };
+        let mut versions = vec![(PersistentHeap::new(), Vec::new())];
+        for _ in 0..2000 {
+            let (heap, mut model) = versions[next() as usize % versions.len()].clone();
+            let (heap, mut model): (PersistentHeap<u32>, Vec<u32>) = match next() % 3 {
+                0 => match heap.pop_min() {
+                    Some((min, rest)) => {
+                        assert_eq!(*min, model.remove(0));
+                        (rest, model)
+                    }
+                    None => (heap, model),
+                },
+                _ => {
+                    let val = next() % 1000;
+                    model.push(val);
+                    (heap.insert(val), model)
+                }
+            };
+            model.sort();
+            assert_eq!(heap.len(), model.len());
+            assert_eq!(heap.peek_min(), model.first());
+            versions.push((heap, model));
+        }
+        for (heap, model) in &versions {
+            assert_eq!(heap.iter().cloned().collect::<Vec<_>>(), *model);
+        }
+    }
+
+    #[test]
+    fn long_heap_test() {
+        // a million elements: building, popping a few and dropping does not recurse
+        let heap: PersistentHeap<u32> = (0..1_000_000).rev().collect();
+        let mut rest = heap.clone();
+        for i in 0..1000 {
+            let (min, next) = rest.pop_min().unwrap();
+            assert_eq!(*min, i);
+            rest = next;
+        }
+        drop(heap);
+        assert_eq!(rest.len(), 999_000);
+        let sorted: PersistentHeap<u32> = (0..1_000_000).collect();
+        drop(sorted);
+    }
+
+    thread_local! {
+        static CLONES: Cell<usize> = const { Cell::new(0) };
+    }
+
+    #[derive(PartialEq, Eq, PartialOrd, Ord, Debug)]
+    struct Counted(u32);
+
+    impl Clone for Counted {
+        fn clone(&self) -> Self {
+            CLONES.with(|clones| clones.set(clones.get() + 1));
+            Counted(self.0)
+        }
+    }
+
+    #[test]
+    fn move_test() {
+        // building from owned values needs no Clone at all
+        #[derive(PartialEq, Eq, PartialOrd, Ord)]
+        struct Plain(u32);
+        let plain: PersistentHeap<Plain> = (0..100).rev().map(Plain).collect();
+        assert_eq!(plain.peek_min().map(|min| min.0), Some(0));
+
+        // a heap no other version shares moves its values around instead of cloning them
+        let mut heap: PersistentHeap<Counted> = (0..1000).map(|i| Counted(i * 7 % 1000)).collect();
+        for i in 1000..2000 {
+            heap.push(Counted(i));
+        }
+        for i in 0..1500 {
+            assert_eq!(heap.pop(), Some(Counted(i)));
+        }
+        assert_eq!(CLONES.with(Cell::get), 0);
+
+        // with an old version around, only the shared nodes on the walked spine are cloned
+        let old = heap.clone();
+        heap.push(Counted(5000));
+        let copied = CLONES.with(Cell::get);
+        assert!(copied > 0 && copied <= 10);
+        heap.push(Counted(5001));
+        assert!(CLONES.with(Cell::get) - copied <= 10);
+        assert_eq!(heap.pop(), Some(Counted(1500)));
+        assert_eq!(old.len(), 500);
+        assert_eq!(old.peek_min(), Some(&Counted(1500)));
+    }
+}
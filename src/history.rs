@@ -0,0 +1,354 @@
+use crate::third::List;
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fmt;
+use std::rc::Rc;
+
+// undo/redo history over persistent third::List snapshots
+//
+// every committed state is kept as a whole List<S>, but since a new state is usually built by prepending to
+// (or taking the tail of) the previous one, consecutive snapshots share everything except the changed prefix.
+// versions form a tree: committing after an undo starts a new branch instead of throwing the redo history away
+struct Version<S> {
+    state: List<S>,
+    parent: Option<usize>,
+    children: Vec<usize>,
+    // the child redo() goes to, that is the branch visited most recently
+    redo: Option<usize>,
+}
+
+pub struct History<S> {
+    versions: HashMap<usize, Version<S>>,
+    current: usize,
+    next_id: usize,
+    // how many undo steps are kept, older versions are dropped together with the branches hanging off them
+    max_depth: Option<usize>,
+    checkpoints: HashMap<String, usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HistoryError {
+    UnknownCheckpoint(String),
+    // the checkpoint existed, but its version fell off the bounded history
+    Expired(String),
+    NoSuchBranch(usize),
+}
+
+impl fmt::Display for HistoryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HistoryError::UnknownCheckpoint(name) => write!(f, "unknown checkpoint {:?}", name),
+            HistoryError::Expired(name) => {
+                write!(f, "checkpoint {:?} is no longer in history", name)
+            }
+            HistoryError::NoSuchBranch(index) => write!(f, "no redo branch {}", index),
+        }
+    }
+}
+
+impl Error for HistoryError {}
+
+impl<S> History<S> {
+    pub fn new(initial: List<S>) -> Self {
+        let mut versions = HashMap::new();
+        versions.insert(
+            0,
+            Version {
+                state: initial,
+                parent: None,
+                children: Vec::new(),
+                redo: None,
+            },
+        );
+        History {
+            versions,
+            current: 0,
+            next_id: 1,
+            max_depth: None,
+            checkpoints: HashMap::new(),
+        }
+    }
+
+    pub fn with_max_depth(initial: List<S>, max_depth: usize) -> Self {
+        History {
+            max_depth: Some(max_depth),
+            ..History::new(initial)
+        }
+    }
+
+    pub fn current(&self) -> &List<S> {
+        &self.version(self.current).state
+    }
+
+    pub fn commit(&mut self, state: List<S>) {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.versions.insert(
+            id,
+            Version {
+                state,
+                parent: Some(self.current),
+                children: Vec::new(),
+                redo: None,
+            },
+        );
+        let parent = self.version_mut(self.current);
+        parent.children.push(id);
+        parent.redo = Some(id);
+        self.current = id;
+        self.prune();
+    }
+
+    pub fn can_undo(&self) -> bool {
+        self.version(self.current).parent.is_some()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        self.version(self.current).redo.is_some()
+    }
+
+    pub fn undo(&mut self) -> bool {
+        match self.version(self.current).parent {
+            Some(parent) => {
+                let child = self.current;
+                self.version_mut(parent).redo = Some(child);
+                self.current = parent;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn redo(&mut self) -> bool {
+        match self.version(self.current).redo {
+            Some(child) => {
+                self.current = child;
+                true
+            }
+            None => false,
+        }
+    }
+
+    // the states redo could go to, oldest branch first
+    pub fn branches(&self) -> Vec<&List<S>> {
+        self.version(self.current)
+            .children
+            .iter()
+            .map(|id| &self.version(*id).state)
+            .collect()
+    }
+
+    pub fn redo_branch(&mut self, index: usize) -> Result<(), HistoryError> {
+        let child = *self
+            .version(self.current)
+            .children
+            .get(index)
+            .ok_or(HistoryError::NoSuchBranch(index))?;
+        self.version_mut(self.current).redo = Some(child);
+        self.current = child;
+        Ok(())
+    }
+
+    // number of undo steps available from the current state
+    pub fn depth(&self) -> usize {
+        self.ancestors(self.current).count()
+    }
+
+    pub fn len(&self) -> usize {
+        self.versions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.versions.is_empty()
+    }
+
+    pub fn checkpoint(&mut self, name: &str) {
+        self.checkpoints.insert(name.to_string(), self.current);
+    }
+
+    pub fn restore(&mut self, name: &str) -> Result<(), HistoryError> {
+        let id = *self
+            .checkpoints
+            .get(name)
+            .ok_or_else(|| HistoryError::UnknownCheckpoint(name.to_string()))?;
+        if !self.versions.contains_key(&id) {
+            return Err(HistoryError::Expired(name.to_string()));
+        }
+        self.current = id;
+        Ok(())
+    }
+
+    // distinct third::List nodes kept alive by all snapshots together,
+    // shared tails are counted once, which is what the history really costs
+    pub fn retained_nodes(&self) -> usize {
+        let mut seen = HashSet::new();
+        for version in self.versions.values() {
+            let mut link = version.state.head.as_ref();
+            while let Some(node) = link {
+                if !seen.insert(Rc::as_ptr(node)) {
+                    break;
+                }
+                link = node.next.as_ref();
+            }
+        }
+        seen.len()
+    }
+
+    fn version(&self, id: usize) -> &Version<S> {
+        &self.versions[&id]
+    }
+
+    fn version_mut(&mut self, id: usize) -> &mut Version<S> {
+        self.versions.get_mut(&id).unwrap()
+    }
+
+    fn ancestors(&self, id: usize) -> impl Iterator<Item = usize> + '_ {
+        std::iter::successors(self.version(id).parent, move |id| self.version(*id).parent)
+    }
+
+    // once the current state is more than max_depth undos away from the root,
+    // the ancestor max_depth steps up becomes the new root and everything outside its subtree is dropped
+    fn prune(&mut self) {
+        let max_depth = match self.max_depth {
+            Some(max_depth) if self.depth() > max_depth => max_depth,
+            _ => return,
+        };
+        let root = match max_depth {
+            0 => self.current,
+            _ => self.ancestors(self.current).nth(max_depth - 1).unwrap(),
+        };
+
+        let mut keep = HashSet::new();
+        let mut stack = vec![root];
+        while let Some(id) = stack.pop() {
+            keep.insert(id);
+            stack.extend(self.version(id).children.iter().cloned());
+        }
+        self.versions.retain(|id, _| keep.contains(id));
+        self.version_mut(root).parent = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{History, HistoryError};
+    use crate::third::List;
+
+    fn contents(list: &List<char>) -> String {
+        list.iter().collect()
+    }
+
+    #[test]
+    fn undo_redo_test() {
+        let mut history = History::new(List::new());
+        assert!(!history.undo());
+        assert!(!history.redo());
+
+        let a = history.current().clone().prepend('a');
+        history.commit(a);
+        let ab = history.current().clone().prepend('b');
+        history.commit(ab);
+        assert_eq!(contents(history.current()), "ba");
+
+        assert!(history.undo());
+        assert_eq!(contents(history.current()), "a");
+        assert!(history.undo());
+        assert_eq!(contents(history.current()), "");
+        assert!(!history.can_undo());
+
+        assert!(history.redo());
+        assert!(history.redo());
+        assert_eq!(contents(history.current()), "ba");
+        assert!(!history.can_redo());
+    }
+
+    #[test]
+    fn branch_test() {
+        let mut history = History::new(List::new().prepend('a'));
+        let b = history.current().clone().prepend('b');
+        history.commit(b);
+        history.undo();
+
+        // committing after an undo keeps the old future as another branch
+        let c = history.current().clone().prepend('c');
+        history.commit(c);
+        assert_eq!(contents(history.current()), "ca");
+
+        history.undo();
+        let branches: Vec<String> = history.branches().into_iter().map(contents).collect();
+        assert_eq!(branches, vec!["ba", "ca"]);
+
+        // redo follows the branch visited last
+        assert!(history.redo());
+        assert_eq!(contents(history.current()), "ca");
+
+        history.undo();
+        history.redo_branch(0).unwrap();
+        assert_eq!(contents(history.current()), "ba");
+        history.undo();
+        assert_eq!(history.redo_branch(5), Err(HistoryError::NoSuchBranch(5)));
+    }
+
+    #[test]
+    fn checkpoint_test() {
+        let mut history = History::with_max_depth(List::new(), 3);
+        history.checkpoint("empty");
+        for c in "abc".chars() {
+            let next = history.current().clone().prepend(c);
+            history.commit(next);
+        }
+        history.checkpoint("abc");
+        assert_eq!(
+            history.restore("nope"),
+            Err(HistoryError::UnknownCheckpoint("nope".into()))
+        );
+
+        history.restore("empty").unwrap();
+        assert_eq!(contents(history.current()), "");
+        history.restore("abc").unwrap();
+        assert_eq!(contents(history.current()), "cba");
+
+        // one more commit pushes the initial state out of the bounded history
+        let next = history.current().clone().prepend('d');
+        history.commit(next);
+        assert_eq!(history.depth(), 3);
+        assert_eq!(
+            history.restore("empty"),
+            Err(HistoryError::Expired("empty".into()))
+        );
+        history.restore("abc").unwrap();
+    }
+
+    #[test]
+    fn bounded_depth_test() {
+        let mut history = History::with_max_depth(List::new(), 10);
+        for i in 0..1000 {
+            let next = history.current().clone().prepend(i);
+            history.commit(next);
+        }
+        assert_eq!(history.depth(), 10);
+        assert_eq!(history.len(), 11);
+        while history.undo() {}
+        assert_eq!(history.current().head(), Some(&989));
+    }
+
+    #[test]
+    fn sharing_test() {
+        let mut history = History::new(List::new());
+        for i in 0..2000 {
+            let next = history.current().clone().prepend(i);
+            history.commit(next);
+        }
+        // 2001 snapshots holding 2000 * 2001 / 2 elements in total, yet one node per edit
+        assert_eq!(history.len(), 2001);
+        assert_eq!(history.retained_nodes(), 2000);
+
+        // branching off an old state only adds the new prefix
+        for _ in 0..1000 {
+            history.undo();
+        }
+        let branch = history.current().clone().tail().prepend(-1).prepend(-2);
+        history.commit(branch);
+        assert_eq!(history.retained_nodes(), 2002);
+    }
+}
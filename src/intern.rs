@@ -0,0 +1,198 @@
+use crate::third::{List, Node};
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::rc::{Rc, Weak};
+
+// hash-consing for third::List
+//
+// every node made through the interner is looked up by (value, address of its tail) first, and if an equal
+// node is still alive that node is handed out again instead of a new one. since tails are interned too,
+// two lists built through the same interner hold equal elements exactly when they start with the same node,
+// so comparing them is List::ptr_eq, whatever their length.
+//
+// the table only holds Weak references: it never keeps a node alive, and entries of dropped nodes are swept
+// out by collect_garbage() (which also runs on its own whenever the table has doubled since the last sweep).
+// keying on the tail address is safe because a live node keeps its tail alive, so as long as an entry can be
+// upgraded its tail address cannot have been reused by another node
+pub struct Interner<T> {
+    table: HashMap<(T, usize), Weak<Node<T>>>,
+    sweep_at: usize,
+    requests: u64,
+    hits: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct InternStats {
+    // how many nodes were asked for, and how many of them were served by an existing node
+    pub requests: u64,
+    pub hits: u64,
+    // nodes currently alive, and table entries including dead ones not swept yet
+    pub live_nodes: usize,
+    pub table_entries: usize,
+}
+
+impl InternStats {
+    // share of requests that did not allocate, 0.0 before the first request
+    pub fn dedup_ratio(&self) -> f64 {
+        match self.requests {
+            0 => 0.0,
+            requests => self.hits as f64 / requests as f64,
+        }
+    }
+}
+
+const MIN_SWEEP: usize = 64;
+
+fn addr<T>(list: &List<T>) -> usize {
+    list.head
+        .as_ref()
+        .map_or(0, |node| Rc::as_ptr(node) as usize)
+}
+
+impl<T: Clone + Hash + Eq> Interner<T> {
+    pub fn new() -> Self {
+        Interner {
+            table: HashMap::new(),
+            sweep_at: MIN_SWEEP,
+            requests: 0,
+            hits: 0,
+        }
+    }
+
+    // like tail.prepend(val), but reuses the node if an equal one is alive.
+    // the tail should come from this interner as well, otherwise only lists sharing that very tail are deduplicated
+    pub fn cons(&mut self, val: T, tail: &List<T>) -> List<T> {
+        self.requests += 1;
+        let key = (val, addr(tail));
+        if let Some(node) = self.table.get(&key).and_then(Weak::upgrade) {
+            self.hits += 1;
+            return List { head: Some(node) };
+        }
+
+        if self.table.len() >= self.sweep_at {
+            self.collect_garbage();
+            self.sweep_at = (self.table.len() * 2).max(MIN_SWEEP);
+        }
+        let list = tail.clone().prepend(key.0.clone());
+        if let Some(node) = &list.head {
+            self.table.insert(key, Rc::downgrade(node));
+        }
+        list
+    }
+
+    // the canonical copy of list: built from the back, so the shared suffixes are found first
+    pub fn intern(&mut self, list: &List<T>) -> List<T> {
+        let vals: Vec<&T> = list.iter().collect();
+        vals.into_iter()
+            .rev()
+            .fold(List::new(), |tail, val| self.cons(val.clone(), &tail))
+    }
+
+    pub fn from_iter<I>(&mut self, iter: I) -> List<T>
+    where
+        I: IntoIterator<Item = T>,
+        I::IntoIter: DoubleEndedIterator,
+    {
+        iter.into_iter()
+            .rev()
+            .fold(List::new(), |tail, val| self.cons(val, &tail))
+    }
+
+    // drops the entries of nodes nobody holds anymore, returns how many were removed
+    pub fn collect_garbage(&mut self) -> usize {
+        let before = self.table.len();
+        self.table.retain(|_, node| node.strong_count() > 0);
+        before - self.table.len()
+    }
+
+    pub fn stats(&self) -> InternStats {
+        InternStats {
+            requests: self.requests,
+            hits: self.hits,
+            live_nodes: self
+                .table
+                .values()
+                .filter(|node| node.strong_count() > 0)
+                .count(),
+            table_entries: self.table.len(),
+        }
+    }
+}
+
+impl<T: Clone + Hash + Eq> Default for Interner<T> {
+    fn default() -> Self {
+        Interner::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Interner;
+    use crate::third::List;
+
+    #[test]
+    fn dedup_test() {
+        let mut interner = Interner::new();
+        let mut a = interner.from_iter(vec![1, 2, 3]);
+        let b = interner.from_iter(vec![1, 2, 3]);
+        assert!(a.ptr_eq(&b));
+
+        // same suffix, different head: only the head node is new
+        let mut c = interner.from_iter(vec![0, 2, 3]);
+        assert!(!c.ptr_eq(&a));
+        assert!(c.tail().ptr_eq(&a.tail()));
+
+        // equal values on different tails stay different nodes
+        let d = interner.from_iter(vec![1, 3]);
+        assert!(!d.ptr_eq(&a));
+        assert_eq!(d.head(), a.head());
+
+        // an ordinary list interns to the canonical copy
+        let plain = List::new().prepend(3).prepend(2).prepend(1);
+        assert!(interner.intern(&plain).ptr_eq(&a));
+        assert!(!plain.ptr_eq(&a));
+    }
+
+    #[test]
+    fn gc_test() {
+        let mut interner = Interner::new();
+        let mut keep = interner.from_iter(vec!["x", "y"]);
+        let drop_me = interner.from_iter(vec!["a", "b", "y"]);
+        assert_eq!(interner.stats().live_nodes, 4);
+
+        drop(drop_me);
+        let stats = interner.stats();
+        assert_eq!(stats.live_nodes, 2);
+        assert_eq!(stats.table_entries, 4);
+        assert_eq!(interner.collect_garbage(), 2);
+        assert_eq!(interner.stats().table_entries, 2);
+
+        // a dead entry is replaced, not resurrected
+        let mut again = interner.from_iter(vec!["b", "y"]);
+        assert_eq!(again.iter().collect::<Vec<_>>(), vec![&"b", &"y"]);
+        assert!(again.tail().ptr_eq(&keep.tail()));
+    }
+
+    #[test]
+    fn stats_test() {
+        let mut interner = Interner::new();
+        assert_eq!(interner.stats().dedup_ratio(), 0.0);
+
+        // 1000 lists of length 10 over 10 distinct suffix chains
+        let lists: Vec<List<u32>> = (0..1000)
+            .map(|i| interner.from_iter((0..10).map(|j| (i % 10) * 100 + j)))
+            .collect();
+        let stats = interner.stats();
+        assert_eq!(stats.requests, 10_000);
+        assert_eq!(stats.live_nodes, 100);
+        assert_eq!(stats.dedup_ratio(), 0.99);
+        assert!(lists[3].ptr_eq(&lists[993]));
+
+        // the table sweeps itself, so churning through short-lived lists does not grow it
+        drop(lists);
+        for i in 0..10_000 {
+            interner.from_iter(vec![i, i + 1]);
+        }
+        assert!(interner.stats().table_entries < 1000);
+    }
+}
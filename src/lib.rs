@@ -3,3 +3,19 @@ pub mod second;
 pub mod third;
 pub mod fourth;
 pub mod fifth;
+pub mod queue;
+pub mod random_access;
+pub mod stream;
+pub mod lisp;
+pub mod env;
+pub mod history;
+pub mod serialize;
+pub mod intern;
+pub mod arena;
+pub mod render;
+pub mod sha256;
+pub mod hash_chain;
+pub mod heap;
+pub mod lru;
+pub mod linked_hash_map;
+pub mod sync_deque;
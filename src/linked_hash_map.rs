@@ -0,0 +1,298 @@
+use crate::fourth::{self, List, NodeHandle};
+use crate::lru::{Key, Lookup, Query};
+use std::borrow::Borrow;
+use std::cell::{Ref, RefMut};
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::iter::FromIterator;
+use std::rc::Rc;
+
+// a hash map that remembers insertion order, on top of fourth::List
+//
+// the same layout as lru::LruCache: the entries sit in a list in insertion order and the map finds the node
+// of a key through a NodeHandle, so insert, lookups and removal anywhere are all O(1), and iterating is a
+// walk along the list. keys are shared through an Rc between the map and their list entry, and can be
+// looked up by anything they borrow as (see lru::Key)
+pub struct LinkedHashMap<K, V> {
+    list: List<(Rc<K>, V)>,
+    map: HashMap<Key<K>, NodeHandle<(Rc<K>, V)>>,
+}
+
+impl<K: Hash + Eq, V> LinkedHashMap<K, V> {
+    pub fn new() -> Self {
+        LinkedHashMap {
+            list: List::new(),
+            map: HashMap::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.map.contains_key(&Lookup(key) as &dyn Query<Q>)
+    }
+
+    // a new key goes to the back, an existing one keeps its place and gets the new value.
+    // returns the value it replaced, if any
+    pub fn insert(&mut self, key: K, val: V) -> Option<V> {
+        match self.map.get(&Lookup(&key) as &dyn Query<K>) {
+            // handles in the map always point into our own list, so they resolve
+            Some(handle) => Some(std::mem::replace(
+                &mut handle.get_mut(&mut self.list).unwrap().1,
+                val,
+            )),
+            None => {
+                let key = Rc::new(key);
+                let handle = self.list.push_back_handle((key.clone(), val));
+                self.map.insert(Key(key), handle);
+                None
+            }
+        }
+    }
+
+    // like insert, but an existing key is moved to the back as if it had just been inserted
+    pub fn insert_to_back(&mut self, key: K, val: V) -> Option<V> {
+        if let Some(handle) = self.map.get(&Lookup(&key) as &dyn Query<K>) {
+            handle.move_to_back(&mut self.list).unwrap();
+        }
+        self.insert(key, val)
+    }
+
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let handle = self.map.remove(&Lookup(key) as &dyn Query<Q>)?;
+        let (_, val) = handle.remove(&mut self.list).unwrap();
+        Some(val)
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> Option<Ref<'_, V>>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let entry = self
+            .map
+            .get(&Lookup(key) as &dyn Query<Q>)?
+            .get(&self.list)
+            .unwrap();
+        Some(Ref::map(entry, |(_, val)| val))
+    }
+
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<RefMut<'_, V>>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let entry = self
+            .map
+            .get(&Lookup(key) as &dyn Query<Q>)?
+            .get_mut(&mut self.list)
+            .unwrap();
+        Some(RefMut::map(entry, |(_, val)| val))
+    }
+
+    // the oldest entry
+    pub fn front(&self) -> Option<(Ref<'_, K>, Ref<'_, V>)> {
+        self.list.peek_front().map(split)
+    }
+
+    // the newest entry
+    pub fn back(&self) -> Option<(Ref<'_, K>, Ref<'_, V>)> {
+        self.list.peek_back().map(split)
+    }
+
+    pub fn pop_front(&mut self) -> Option<(K, V)> {
+        let entry = self.list.pop_front()?;
+        Some(self.forget(entry))
+    }
+
+    pub fn pop_back(&mut self) -> Option<(K, V)> {
+        let entry = self.list.pop_back()?;
+        Some(self.forget(entry))
+    }
+
+    // drops the map's copy of the key of an entry that has left the list, leaving the entry the only one
+    fn forget(&mut self, (key, val): (Rc<K>, V)) -> (K, V) {
+        self.map.remove(&Lookup(&*key) as &dyn Query<K>);
+        (Rc::try_unwrap(key).ok().unwrap(), val)
+    }
+}
+
+impl<K, V> LinkedHashMap<K, V> {
+    // oldest first, reversible to start from the newest
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = (Ref<'_, K>, Ref<'_, V>)> {
+        self.list.iter().map(split)
+    }
+
+    // a RefMut over the whole entry would let the key be changed under the map, so only the value is
+    // borrowed mutably. the key is handed out as a plain reference instead: the map is borrowed exclusively
+    // for as long as the iterator lives, so the entry stays in the list and its key is never written to
+    pub fn iter_mut(&mut self) -> impl DoubleEndedIterator<Item = (&K, RefMut<'_, V>)> {
+        self.list.iter_mut().map(|entry| {
+            let key = unsafe { &*Rc::as_ptr(&entry.0) };
+            (key, RefMut::map(entry, |(_, val)| val))
+        })
+    }
+}
+
+fn split<K, V>(entry: Ref<'_, (Rc<K>, V)>) -> (Ref<'_, K>, Ref<'_, V>) {
+    Ref::map_split(entry, |(key, val)| (&**key, val))
+}
+
+impl<K: Hash + Eq, V> Default for LinkedHashMap<K, V> {
+    fn default() -> Self {
+        LinkedHashMap::new()
+    }
+}
+
+// later duplicates replace the value but keep the position of the first, as insert does
+impl<K: Hash + Eq, V> FromIterator<(K, V)> for LinkedHashMap<K, V> {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let mut map = LinkedHashMap::new();
+        for (key, val) in iter {
+            map.insert(key, val);
+        }
+        map
+    }
+}
+
+pub struct IntoIter<K, V>(fourth::IntoIter<(Rc<K>, V)>);
+
+impl<K, V> IntoIterator for LinkedHashMap<K, V> {
+    type Item = (K, V);
+    type IntoIter = IntoIter<K, V>;
+
+    // the map goes first, so every key left in the list has a single owner
+    fn into_iter(self) -> IntoIter<K, V> {
+        drop(self.map);
+        IntoIter(self.list.into_iter())
+    }
+}
+
+fn unwrap_key<K, V>((key, val): (Rc<K>, V)) -> (K, V) {
+    (Rc::try_unwrap(key).ok().unwrap(), val)
+}
+
+impl<K, V> Iterator for IntoIter<K, V> {
+    type Item = (K, V);
+    fn next(&mut self) -> Option<(K, V)> {
+        self.0.next().map(unwrap_key)
+    }
+}
+
+impl<K, V> DoubleEndedIterator for IntoIter<K, V> {
+    fn next_back(&mut self) -> Option<(K, V)> {
+        self.0.next_back().map(unwrap_key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::LinkedHashMap;
+
+    fn keys(map: &LinkedHashMap<&'static str, i32>) -> Vec<&'static str> {
+        map.iter().map(|(key, _)| *key).collect()
+    }
+
+    #[test]
+    fn order_test() {
+        let mut map = LinkedHashMap::new();
+        assert!(map.front().is_none());
+        for (i, key) in ["port", "host", "user", "path"].into_iter().enumerate() {
+            assert_eq!(map.insert(key, i as i32), None);
+        }
+        assert_eq!(keys(&map), vec!["port", "host", "user", "path"]);
+
+        // replacing keeps the place, insert_to_back does not
+        assert_eq!(map.insert("port", 10), Some(0));
+        assert_eq!(keys(&map), vec!["port", "host", "user", "path"]);
+        assert_eq!(map.insert_to_back("host", 11), Some(1));
+        assert_eq!(map.insert_to_back("mode", 4), None);
+        assert_eq!(keys(&map), vec!["port", "user", "path", "host", "mode"]);
+
+        // removing from the middle
+        assert_eq!(map.remove(&"path"), Some(3));
+        assert_eq!(map.remove(&"path"), None);
+        assert_eq!(keys(&map), vec!["port", "user", "host", "mode"]);
+        assert_eq!(map.len(), 4);
+        assert!(!map.contains_key(&"path"));
+
+        let front = map.front().map(|(key, val)| (*key, *val));
+        let back = map.back().map(|(key, val)| (*key, *val));
+        assert_eq!(front, Some(("port", 10)));
+        assert_eq!(back, Some(("mode", 4)));
+        assert_eq!(map.pop_front(), Some(("port", 10)));
+        assert_eq!(map.pop_back(), Some(("mode", 4)));
+        assert_eq!(keys(&map), vec!["user", "host"]);
+    }
+
+    #[test]
+    fn access_test() {
+        let mut map: LinkedHashMap<String, Vec<i32>> =
+            (0..5).map(|i| (format!("k{}", i), vec![i])).collect();
+        assert_eq!(*map.get("k2").unwrap(), vec![2]);
+        map.get_mut("k2").unwrap().push(20);
+        assert!(map.get("k9").is_none());
+
+        for (key, mut val) in map.iter_mut().rev() {
+            val.push(key.len() as i32);
+        }
+        assert_eq!(*map.get("k2").unwrap(), vec![2, 20, 2]);
+        assert_eq!(
+            map.iter()
+                .rev()
+                .map(|(key, _)| key.clone())
+                .collect::<Vec<_>>(),
+            vec!["k4", "k3", "k2", "k1", "k0"]
+        );
+
+        let mut entries = map.into_iter();
+        assert_eq!(entries.next(), Some(("k0".to_string(), vec![0, 2])));
+        assert_eq!(entries.next_back(), Some(("k4".to_string(), vec![4, 2])));
+        assert_eq!(entries.count(), 3);
+    }
+
+    #[test]
+    fn churn_test() {
+        // many inserts and removes keep the list and the index in step
+        let mut map = LinkedHashMap::new();
+        let mut model: Vec<(u32, u32)> = Vec::new();
+        for i in 0..2000u32 {
+            let key = i * 7 % 101;
+            match i % 3 {
+                0 => {
+                    let removed = map.remove(&key);
+                    let pos = model.iter().position(|&(k, _)| k == key);
+                    assert_eq!(removed, pos.map(|pos| model.remove(pos).1));
+                }
+                1 => {
+                    map.insert(key, i);
+                    match model.iter_mut().find(|(k, _)| *k == key) {
+                        Some(entry) => entry.1 = i,
+                        None => model.push((key, i)),
+                    }
+                }
+                _ => {
+                    map.insert_to_back(key, i);
+                    model.retain(|&(k, _)| k != key);
+                    model.push((key, i));
+                }
+            }
+        }
+        assert_eq!(map.len(), model.len());
+        assert_eq!(map.into_iter().collect::<Vec<_>>(), model);
+    }
+}
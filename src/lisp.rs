@@ -0,0 +1,889 @@
+use crate::third::List;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::iter::Peekable;
+use std::rc::Rc;
+use std::str::Chars;
+
+// a small lisp whose cons cells are third::List nodes:
+// car is head(), cdr is tail() and cons is prepend(), so (cons 0 (cdr xs)) shares every node of xs but the first.
+// only proper lists exist, consing onto something that is not a list is an error
+//
+// environments are persistent lists too: entering a lambda or a let prepends bindings onto the captured env,
+// so a closure captures its whole scope by cloning a single Rc
+pub type Env = List<(Rc<str>, Value)>;
+
+pub type NativeFn = dyn Fn(&[Value]) -> Result<Value, LispError>;
+
+// how deeply expressions may nest, both when reading and when evaluating. the reader and the evaluator
+// recurse once per level (only tail positions loop), so without a limit deep input overflows the stack.
+// an evaluator level takes a few KiB of stack in debug builds, this stays well inside a 2 MiB thread
+pub const MAX_DEPTH: usize = 256;
+
+#[derive(Clone)]
+pub enum Value {
+    Bool(bool),
+    Int(i64),
+    Str(Rc<str>),
+    Sym(Rc<str>),
+    List(List<Value>),
+    Lambda(Rc<Lambda>),
+    Native(Rc<str>, Rc<NativeFn>),
+}
+
+pub struct Lambda {
+    params: Vec<Rc<str>>,
+    body: List<Value>,
+    env: Env,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LispError {
+    // the input ended in the middle of an expression, a repl should read another line
+    Incomplete,
+    Syntax(String),
+    Unbound(String),
+    Type {
+        expected: &'static str,
+        found: String,
+    },
+    Arity {
+        name: String,
+        expected: usize,
+        found: usize,
+    },
+    DivisionByZero,
+    // nesting beyond MAX_DEPTH, in the source or in non-tail calls
+    TooDeep,
+    Custom(String),
+}
+
+impl fmt::Display for LispError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LispError::Incomplete => write!(f, "unexpected end of input"),
+            LispError::Syntax(msg) => write!(f, "syntax error: {}", msg),
+            LispError::Unbound(name) => write!(f, "unbound symbol: {}", name),
+            LispError::Type { expected, found } => {
+                write!(f, "type error: expected {}, found {}", expected, found)
+            }
+            LispError::Arity {
+                name,
+                expected,
+                found,
+            } => write!(
+                f,
+                "{} expects {} argument(s), got {}",
+                name, expected, found
+            ),
+            LispError::DivisionByZero => write!(f, "division by zero"),
+            LispError::TooDeep => write!(f, "nested more than {} levels deep", MAX_DEPTH),
+            LispError::Custom(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl Error for LispError {}
+
+impl Value {
+    pub fn nil() -> Value {
+        Value::List(List::new())
+    }
+
+    pub fn sym(name: &str) -> Value {
+        Value::Sym(name.into())
+    }
+
+    pub fn list<I: IntoIterator<Item = Value>>(vals: I) -> Value {
+        let vals: Vec<Value> = vals.into_iter().collect();
+        Value::List(
+            vals.into_iter()
+                .rev()
+                .fold(List::new(), |mut list, val| list.prepend(val)),
+        )
+    }
+
+    // only #f is false, like scheme
+    pub fn is_truthy(&self) -> bool {
+        !matches!(self, Value::Bool(false))
+    }
+
+    pub fn as_int(&self) -> Result<i64, LispError> {
+        match self {
+            Value::Int(n) => Ok(*n),
+            other => Err(other.type_error("integer")),
+        }
+    }
+
+    pub fn as_list(&self) -> Result<&List<Value>, LispError> {
+        match self {
+            Value::List(list) => Ok(list),
+            other => Err(other.type_error("list")),
+        }
+    }
+
+    fn type_error(&self, expected: &'static str) -> LispError {
+        LispError::Type {
+            expected,
+            found: self.to_string(),
+        }
+    }
+}
+
+// eq? compares lists by identity, which is exactly third::List::ptr_eq
+fn is_eq(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::List(x), Value::List(y)) => x.ptr_eq(y),
+        _ => atom_eq(a, b),
+    }
+}
+
+// equal? compares lists element by element, shared tails are skipped once the nodes are the same.
+// nested lists are compared from a stack of pairs still to do rather than by recursion: lists built at
+// runtime can nest far deeper than MAX_DEPTH
+fn is_equal(a: &Value, b: &Value) -> bool {
+    let mut pending = vec![(a.clone(), b.clone())];
+    while let Some((a, b)) = pending.pop() {
+        match (&a, &b) {
+            (Value::List(x), Value::List(y)) => {
+                let (mut x, mut y) = (x.clone(), y.clone());
+                while !x.ptr_eq(&y) {
+                    match (x.head(), y.head()) {
+                        (Some(a), Some(b)) => pending.push((a.clone(), b.clone())),
+                        _ => return false,
+                    }
+                    x = x.tail();
+                    y = y.tail();
+                }
+            }
+            _ if !atom_eq(&a, &b) => return false,
+            _ => {}
+        }
+    }
+    true
+}
+
+fn atom_eq(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Bool(x), Value::Bool(y)) => x == y,
+        (Value::Int(x), Value::Int(y)) => x == y,
+        (Value::Str(x), Value::Str(y)) => x == y,
+        (Value::Sym(x), Value::Sym(y)) => x == y,
+        (Value::Lambda(x), Value::Lambda(y)) => Rc::ptr_eq(x, y),
+        (Value::Native(_, x), Value::Native(_, y)) => Rc::ptr_eq(x, y),
+        _ => false,
+    }
+}
+
+// printing keeps the lists it is in the middle of on a stack, for the same reason as is_equal:
+// each entry is what is left of an open list, and whether anything of it has been written yet
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut open: Vec<(List<Value>, bool)> = Vec::new();
+        let mut next = Some(self.clone());
+        loop {
+            if let Some(val) = next.take() {
+                match &val {
+                    Value::Bool(true) => write!(f, "#t")?,
+                    Value::Bool(false) => write!(f, "#f")?,
+                    Value::Int(n) => write!(f, "{}", n)?,
+                    Value::Str(s) => write!(f, "{:?}", s)?,
+                    Value::Sym(s) => write!(f, "{}", s)?,
+                    Value::List(list) => {
+                        write!(f, "(")?;
+                        open.push((list.clone(), false));
+                    }
+                    Value::Lambda(_) => write!(f, "#<lambda>")?,
+                    Value::Native(name, _) => write!(f, "#<native {}>", name)?,
+                }
+            }
+            let Some((rest, started)) = open.last_mut() else {
+                return Ok(());
+            };
+            match rest.head().cloned() {
+                Some(val) => {
+                    if *started {
+                        write!(f, " ")?;
+                    }
+                    *started = true;
+                    *rest = rest.tail();
+                    next = Some(val);
+                }
+                None => {
+                    write!(f, ")")?;
+                    open.pop();
+                }
+            }
+        }
+    }
+}
+
+// dropping a list drops its elements, so a list nested n deep would be dropped n rust frames deep.
+// instead, the nodes nobody else shares are taken apart here, and the lists found in them are put on
+// a stack to be taken apart in turn. a node that is still shared ends the walk down its list
+impl Drop for Value {
+    fn drop(&mut self) {
+        let Value::List(list) = self else {
+            return;
+        };
+        let mut stack = vec![std::mem::take(list)];
+        while let Some(mut list) = stack.pop() {
+            let mut link = list.head.take();
+            while let Some(node) = link {
+                let Ok(mut node) = Rc::try_unwrap(node) else {
+                    break;
+                };
+                link = node.next.take();
+                if let Value::List(inner) = &mut node.val {
+                    stack.push(std::mem::take(inner));
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Debug for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self)
+    }
+}
+
+// reader: turns source text into values, a quoted form 'x is read as (quote x)
+struct Reader<'a> {
+    chars: Peekable<Chars<'a>>,
+    depth: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn skip_whitespace(&mut self) {
+        while let Some(&c) = self.chars.peek() {
+            if c == ';' {
+                while !matches!(self.chars.next(), Some('\n') | None) {}
+            } else if c.is_whitespace() {
+                self.chars.next();
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn read(&mut self) -> Result<Option<Value>, LispError> {
+        self.skip_whitespace();
+        match self.chars.peek() {
+            None => Ok(None),
+            Some(_) => self.read_expr().map(Some),
+        }
+    }
+
+    fn read_expr(&mut self) -> Result<Value, LispError> {
+        if self.depth >= MAX_DEPTH {
+            return Err(LispError::TooDeep);
+        }
+        self.depth += 1;
+        let val = self.read_nested();
+        self.depth -= 1;
+        val
+    }
+
+    fn read_nested(&mut self) -> Result<Value, LispError> {
+        self.skip_whitespace();
+        match self.chars.next() {
+            None => Err(LispError::Incomplete),
+            Some('(') => {
+                let mut vals = Vec::new();
+                loop {
+                    self.skip_whitespace();
+                    match self.chars.peek() {
+                        None => return Err(LispError::Incomplete),
+                        Some(')') => {
+                            self.chars.next();
+                            return Ok(Value::list(vals));
+                        }
+                        Some(_) => vals.push(self.read_expr()?),
+                    }
+                }
+            }
+            Some(')') => Err(LispError::Syntax("unexpected )".to_string())),
+            Some('\'') => Ok(Value::list(vec![Value::sym("quote"), self.read_expr()?])),
+            Some('"') => {
+                let mut s = String::new();
+                loop {
+                    match self.chars.next() {
+                        None => return Err(LispError::Incomplete),
+                        Some('"') => return Ok(Value::Str(s.into())),
+                        Some('\\') => match self.chars.next() {
+                            None => return Err(LispError::Incomplete),
+                            Some('n') => s.push('\n'),
+                            Some(c) => s.push(c),
+                        },
+                        Some(c) => s.push(c),
+                    }
+                }
+            }
+            Some(c) => {
+                let mut atom = c.to_string();
+                while let Some(&c) = self.chars.peek() {
+                    if c.is_whitespace() || "()'\";".contains(c) {
+                        break;
+                    }
+                    atom.push(c);
+                    self.chars.next();
+                }
+                Ok(parse_atom(atom))
+            }
+        }
+    }
+}
+
+fn parse_atom(atom: String) -> Value {
+    match atom.as_str() {
+        "#t" => Value::Bool(true),
+        "#f" => Value::Bool(false),
+        _ => match atom.parse() {
+            Ok(n) => Value::Int(n),
+            Err(_) => Value::Sym(atom.into()),
+        },
+    }
+}
+
+pub fn read_all(src: &str) -> Result<Vec<Value>, LispError> {
+    let mut reader = Reader {
+        chars: src.chars().peekable(),
+        depth: 0,
+    };
+    let mut vals = Vec::new();
+    while let Some(val) = reader.read()? {
+        vals.push(val);
+    }
+    Ok(vals)
+}
+
+pub struct Interpreter {
+    // top-level defines, looked up after the local environment
+    // closures resolve globals when they are called, so recursive and mutually recursive defines just work.
+    // unlike local scopes nothing captures the globals, so a redefinition simply replaces the old value
+    globals: HashMap<Rc<str>, Value>,
+    // eval_in calls currently running
+    depth: usize,
+}
+
+impl Interpreter {
+    pub fn new() -> Self {
+        let mut interp = Interpreter {
+            globals: HashMap::new(),
+            depth: 0,
+        };
+        interp.define_builtins();
+        interp
+    }
+
+    pub fn define(&mut self, name: &str, val: Value) {
+        self.globals.insert(name.into(), val);
+    }
+
+    // host functions let the embedding application expose its own data to rules
+    pub fn define_fn<F>(&mut self, name: &str, f: F)
+    where
+        F: Fn(&[Value]) -> Result<Value, LispError> + 'static,
+    {
+        self.define(name, Value::Native(name.into(), Rc::new(f)));
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        self.globals.get(name)
+    }
+
+    // evaluates every form in src and returns the value of the last one
+    pub fn eval_str(&mut self, src: &str) -> Result<Value, LispError> {
+        let mut result = Value::nil();
+        for expr in read_all(src)? {
+            result = self.eval(&expr)?;
+        }
+        Ok(result)
+    }
+
+    pub fn eval(&mut self, expr: &Value) -> Result<Value, LispError> {
+        self.eval_in(expr.clone(), List::new())
+    }
+
+    fn eval_in(&mut self, expr: Value, env: Env) -> Result<Value, LispError> {
+        if self.depth >= MAX_DEPTH {
+            return Err(LispError::TooDeep);
+        }
+        self.depth += 1;
+        let val = self.eval_loop(expr, env);
+        self.depth -= 1;
+        val
+    }
+
+    // tail positions (if branches, the last form of a body, lambda application) loop instead of recursing,
+    // so tail-recursive lisp loops run in constant rust stack
+    fn eval_loop(&mut self, mut expr: Value, mut env: Env) -> Result<Value, LispError> {
+        loop {
+            let mut list = match &expr {
+                Value::Sym(name) => {
+                    return lookup(&env, name)
+                        .or_else(|| self.globals.get(name))
+                        .cloned()
+                        .ok_or_else(|| LispError::Unbound(name.to_string()))
+                }
+                Value::List(list) => list.clone(),
+                _ => return Ok(expr),
+            };
+            let head = match list.head() {
+                // () evaluates to itself
+                None => return Ok(Value::List(list)),
+                Some(head) => head.clone(),
+            };
+            let mut args = list.tail();
+
+            if let Value::Sym(form) = &head {
+                match &**form {
+                    "quote" => return Ok(nth(&args, 0, "quote", 1)?.clone()),
+                    "if" => {
+                        let cond = self.eval_in(nth(&args, 0, "if", 2)?.clone(), env.clone())?;
+                        let mut rest = args.tail();
+                        expr = if cond.is_truthy() {
+                            nth(&rest, 0, "if", 2)?.clone()
+                        } else {
+                            rest.tail().head().cloned().unwrap_or_else(Value::nil)
+                        };
+                        continue;
+                    }
+                    "define" => return self.eval_define(&args, &env),
+                    "lambda" => {
+                        let params = nth(&args, 0, "lambda", 2)?;
+                        return make_lambda(params, args.clone().tail(), &env);
+                    }
+                    "let" => {
+                        let mut scope = env.clone();
+                        for binding in nth(&args, 0, "let", 2)?.as_list()?.iter() {
+                            let binding = binding.as_list()?;
+                            let name = symbol(nth(binding, 0, "let binding", 2)?)?;
+                            let val = nth(binding, 1, "let binding", 2)?.clone();
+                            // bindings are evaluated in the outer scope, like scheme's let
+                            scope = scope.prepend((name, self.eval_in(val, env.clone())?));
+                        }
+                        env = scope;
+                        expr = self.eval_body(&args.tail(), &env)?;
+                        continue;
+                    }
+                    "begin" => {
+                        expr = self.eval_body(&args, &env)?;
+                        continue;
+                    }
+                    _ => {}
+                }
+            }
+
+            let func = self.eval_in(head, env.clone())?;
+            let mut argv = Vec::new();
+            for arg in args.iter() {
+                argv.push(self.eval_in(arg.clone(), env.clone())?);
+            }
+            match &func {
+                Value::Native(_, f) => return f(&argv),
+                Value::Lambda(lambda) => {
+                    if lambda.params.len() != argv.len() {
+                        return Err(LispError::Arity {
+                            name: "lambda".to_string(),
+                            expected: lambda.params.len(),
+                            found: argv.len(),
+                        });
+                    }
+                    env = lambda.env.clone();
+                    for (param, arg) in lambda.params.iter().zip(argv) {
+                        env = env.prepend((param.clone(), arg));
+                    }
+                    expr = self.eval_body(&lambda.body, &env)?;
+                }
+                other => return Err(other.type_error("procedure")),
+            }
+        }
+    }
+
+    // evaluates all forms but the last one and hands the last one back to the caller's loop
+    fn eval_body(&mut self, body: &List<Value>, env: &Env) -> Result<Value, LispError> {
+        let mut forms = body.iter().peekable();
+        while let Some(form) = forms.next() {
+            if forms.peek().is_none() {
+                return Ok(form.clone());
+            }
+            self.eval_in(form.clone(), env.clone())?;
+        }
+        Ok(Value::nil())
+    }
+
+    // (define name expr) or (define (name params...) body...), always binds a global
+    fn eval_define(&mut self, args: &List<Value>, env: &Env) -> Result<Value, LispError> {
+        let (name, val) = match nth(args, 0, "define", 2)? {
+            Value::List(signature) => {
+                let name = symbol(nth(signature, 0, "define", 1)?)?;
+                let params = Value::List(signature.clone().tail());
+                (name, make_lambda(&params, args.clone().tail(), env)?)
+            }
+            target => {
+                let name = symbol(target)?;
+                let expr = nth(args, 1, "define", 2)?.clone();
+                (name, self.eval_in(expr, env.clone())?)
+            }
+        };
+        self.globals.insert(name.clone(), val);
+        Ok(Value::Sym(name))
+    }
+
+    fn define_builtins(&mut self) {
+        self.define_fn("+", |args| fold_ints(args, 0, |a, b| Ok(a.wrapping_add(b))));
+        self.define_fn("*", |args| fold_ints(args, 1, |a, b| Ok(a.wrapping_mul(b))));
+        self.define_fn("-", |args| match args {
+            [single] => Ok(Value::Int(single.as_int()?.wrapping_neg())),
+            [first, rest @ ..] => fold_ints(rest, first.as_int()?, |a, b| Ok(a.wrapping_sub(b))),
+            [] => Err(arity("-", 1, 0)),
+        });
+        self.define_fn("/", |args| match args {
+            [first, rest @ ..] if !rest.is_empty() => fold_ints(rest, first.as_int()?, |a, b| {
+                a.checked_div(b).ok_or(LispError::DivisionByZero)
+            }),
+            _ => Err(arity("/", 2, args.len())),
+        });
+        self.define_fn("=", |args| compare_ints(args, |a, b| a == b));
+        self.define_fn("<", |args| compare_ints(args, |a, b| a < b));
+        self.define_fn(">", |args| compare_ints(args, |a, b| a > b));
+        self.define_fn("<=", |args| compare_ints(args, |a, b| a <= b));
+        self.define_fn(">=", |args| compare_ints(args, |a, b| a >= b));
+        self.define_fn("car", |args| {
+            let list = exactly::<1>("car", args)?[0].as_list()?;
+            list.head()
+                .cloned()
+                .ok_or_else(|| Value::nil().type_error("non-empty list"))
+        });
+        self.define_fn("cdr", |args| {
+            let list = exactly::<1>("cdr", args)?[0].as_list()?;
+            match list.head() {
+                Some(_) => Ok(Value::List(list.clone().tail())),
+                None => Err(Value::nil().type_error("non-empty list")),
+            }
+        });
+        self.define_fn("cons", |args| {
+            let [val, list] = exactly::<2>("cons", args)?;
+            Ok(Value::List(list.as_list()?.clone().prepend(val.clone())))
+        });
+        self.define_fn("list", |args| Ok(Value::list(args.iter().cloned())));
+        self.define_fn("null?", |args| {
+            let [val] = exactly::<1>("null?", args)?;
+            Ok(Value::Bool(
+                matches!(val, Value::List(list) if list.head().is_none()),
+            ))
+        });
+        self.define_fn("not", |args| {
+            Ok(Value::Bool(!exactly::<1>("not", args)?[0].is_truthy()))
+        });
+        self.define_fn("eq?", |args| {
+            let [a, b] = exactly::<2>("eq?", args)?;
+            Ok(Value::Bool(is_eq(a, b)))
+        });
+        self.define_fn("equal?", |args| {
+            let [a, b] = exactly::<2>("equal?", args)?;
+            Ok(Value::Bool(is_equal(a, b)))
+        });
+    }
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Interpreter::new()
+    }
+}
+
+// the innermost binding is the one closest to the head
+fn lookup<'a>(env: &'a Env, name: &str) -> Option<&'a Value> {
+    env.iter()
+        .find(|(key, _)| &**key == name)
+        .map(|(_, val)| val)
+}
+
+fn nth<'a>(
+    list: &'a List<Value>,
+    index: usize,
+    form: &str,
+    expected: usize,
+) -> Result<&'a Value, LispError> {
+    list.iter().nth(index).ok_or_else(|| LispError::Arity {
+        name: form.to_string(),
+        expected,
+        found: list.iter().count(),
+    })
+}
+
+fn symbol(val: &Value) -> Result<Rc<str>, LispError> {
+    match val {
+        Value::Sym(name) => Ok(name.clone()),
+        other => Err(other.type_error("symbol")),
+    }
+}
+
+fn make_lambda(params: &Value, body: List<Value>, env: &Env) -> Result<Value, LispError> {
+    let params = params
+        .as_list()?
+        .iter()
+        .map(symbol)
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(Value::Lambda(Rc::new(Lambda {
+        params,
+        body,
+        env: env.clone(),
+    })))
+}
+
+fn arity(name: &str, expected: usize, found: usize) -> LispError {
+    LispError::Arity {
+        name: name.to_string(),
+        expected,
+        found,
+    }
+}
+
+fn exactly<'a, const N: usize>(name: &str, args: &'a [Value]) -> Result<&'a [Value; N], LispError> {
+    args.try_into().map_err(|_| arity(name, N, args.len()))
+}
+
+fn fold_ints<F>(args: &[Value], init: i64, f: F) -> Result<Value, LispError>
+where
+    F: Fn(i64, i64) -> Result<i64, LispError>,
+{
+    let mut acc = init;
+    for arg in args {
+        acc = f(acc, arg.as_int()?)?;
+    }
+    Ok(Value::Int(acc))
+}
+
+fn compare_ints<F: Fn(i64, i64) -> bool>(args: &[Value], f: F) -> Result<Value, LispError> {
+    let ints = args
+        .iter()
+        .map(Value::as_int)
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(Value::Bool(ints.windows(2).all(|pair| f(pair[0], pair[1]))))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{read_all, Interpreter, LispError, Value, MAX_DEPTH};
+    use crate::third::List;
+    use std::rc::Rc;
+
+    fn eval(interp: &mut Interpreter, src: &str) -> String {
+        interp.eval_str(src).unwrap().to_string()
+    }
+
+    #[test]
+    fn reader_test() {
+        let mut interp = Interpreter::new();
+        assert_eq!(
+            eval(&mut interp, "'(1 (2 \"three\") #t sym)"),
+            "(1 (2 \"three\") #t sym)"
+        );
+        assert_eq!(eval(&mut interp, "; comment\n'()"), "()");
+        assert_eq!(interp.eval_str("(+ 1").err(), Some(LispError::Incomplete));
+        assert!(matches!(interp.eval_str(")"), Err(LispError::Syntax(_))));
+    }
+
+    #[test]
+    fn arithmetic_test() {
+        let mut interp = Interpreter::new();
+        assert_eq!(eval(&mut interp, "(+ 1 2 (* 3 4))"), "15");
+        assert_eq!(eval(&mut interp, "(- 10 1 2)"), "7");
+        assert_eq!(eval(&mut interp, "(- 5)"), "-5");
+        assert_eq!(eval(&mut interp, "(/ 20 2 5)"), "2");
+        assert_eq!(eval(&mut interp, "(< 1 2 3)"), "#t");
+        assert_eq!(eval(&mut interp, "(>= 1 2)"), "#f");
+        assert_eq!(
+            interp.eval_str("(/ 1 0)").err(),
+            Some(LispError::DivisionByZero)
+        );
+    }
+
+    #[test]
+    fn special_form_test() {
+        let mut interp = Interpreter::new();
+        assert_eq!(eval(&mut interp, "(if (< 1 2) 'yes 'no)"), "yes");
+        assert_eq!(eval(&mut interp, "(if #f 'yes)"), "()");
+        assert_eq!(eval(&mut interp, "(let ((x 2) (y 3)) (* x y))"), "6");
+        assert_eq!(
+            eval(&mut interp, "(define x 10) (let ((x 1) (y x)) y)"),
+            "10"
+        );
+        assert_eq!(eval(&mut interp, "((lambda (a b) (- a b)) 5 3)"), "2");
+        assert_eq!(eval(&mut interp, "(begin 1 2 3)"), "3");
+    }
+
+    #[test]
+    fn closure_test() {
+        let mut interp = Interpreter::new();
+        eval(&mut interp, "(define (make-adder n) (lambda (x) (+ x n)))");
+        eval(&mut interp, "(define add5 (make-adder 5))");
+        eval(&mut interp, "(define n 100)");
+        // the closure sees the n it captured, not the global one
+        assert_eq!(eval(&mut interp, "(add5 1)"), "6");
+
+        eval(
+            &mut interp,
+            "(define (fact n) (if (= n 0) 1 (* n (fact (- n 1)))))",
+        );
+        assert_eq!(eval(&mut interp, "(fact 10)"), "3628800");
+
+        eval(
+            &mut interp,
+            "(define (even? n) (if (= n 0) #t (odd? (- n 1))))",
+        );
+        eval(
+            &mut interp,
+            "(define (odd? n) (if (= n 0) #f (even? (- n 1))))",
+        );
+        assert_eq!(eval(&mut interp, "(even? 10001)"), "#f");
+    }
+
+    #[test]
+    fn cons_sharing_test() {
+        let mut interp = Interpreter::new();
+        eval(&mut interp, "(define xs '(1 2 3))");
+        eval(&mut interp, "(define ys (cons 0 (cdr xs)))");
+        assert_eq!(eval(&mut interp, "ys"), "(0 2 3)");
+        assert_eq!(eval(&mut interp, "(car (cdr ys))"), "2");
+        assert_eq!(eval(&mut interp, "(eq? (cdr xs) (cdr ys))"), "#t");
+        assert_eq!(eval(&mut interp, "(eq? xs '(1 2 3))"), "#f");
+        assert_eq!(eval(&mut interp, "(equal? xs '(1 2 3))"), "#t");
+
+        // cdr and cons really are tail and prepend: both lists share the 2 -> 3 nodes
+        let xs = interp.get("xs").unwrap().as_list().unwrap().clone();
+        let ys = interp.get("ys").unwrap().as_list().unwrap().clone();
+        assert_eq!(List::shared_suffix_len(&xs, &ys), 2);
+    }
+
+    #[test]
+    fn tail_call_test() {
+        let mut interp = Interpreter::new();
+        eval(
+            &mut interp,
+            "(define (build n acc) (if (= n 0) acc (build (- n 1) (cons n acc))))",
+        );
+        eval(
+            &mut interp,
+            "(define (len xs n) (if (null? xs) n (len (cdr xs) (+ n 1))))",
+        );
+        // a deep loop in constant stack, and a long list that is dropped without recursion
+        assert_eq!(eval(&mut interp, "(len (build 200000 '()) 0)"), "200000");
+        eval(&mut interp, "(define big (build 200000 '()))");
+        let big = interp.get("big").unwrap().as_list().unwrap();
+        let first = Rc::downgrade(big.head.as_ref().unwrap());
+        // redefining drops the old value, and the whole list with it
+        eval(&mut interp, "(define big '())");
+        assert!(first.upgrade().is_none());
+    }
+
+    #[test]
+    fn depth_test() {
+        let mut interp = Interpreter::new();
+        let nested = |depth| "(".repeat(depth) + &")".repeat(depth);
+        assert!(read_all(&nested(MAX_DEPTH)).is_ok());
+        assert_eq!(
+            read_all(&nested(MAX_DEPTH + 1)).err(),
+            Some(LispError::TooDeep)
+        );
+        assert_eq!(
+            interp.eval_str(&nested(100_000)).err(),
+            Some(LispError::TooDeep)
+        );
+        let sum = |depth| "(+ 1 ".repeat(depth) + "0" + &")".repeat(depth);
+        assert_eq!(
+            eval(&mut interp, &sum(MAX_DEPTH - 1)),
+            (MAX_DEPTH - 1).to_string()
+        );
+        assert_eq!(
+            interp.eval_str(&sum(MAX_DEPTH)).err(),
+            Some(LispError::TooDeep)
+        );
+
+        // deep non-tail recursion fails the same way, and the interpreter is usable afterwards
+        eval(
+            &mut interp,
+            "(define (count n) (if (= n 0) 0 (+ 1 (count (- n 1)))))",
+        );
+        assert_eq!(
+            interp.eval_str("(count 100000)").err(),
+            Some(LispError::TooDeep)
+        );
+        assert_eq!(eval(&mut interp, "(count 100)"), "100");
+    }
+
+    #[test]
+    fn runtime_nesting_test() {
+        // nesting built by a running program is not bounded by MAX_DEPTH, it must not overflow either
+        let mut interp = Interpreter::new();
+        interp
+            .eval_str(
+                "(define (nest n acc) (if (= n 0) acc (nest (- n 1) (list acc))))
+                 (define a (nest 200000 '()))
+                 (define b (nest 200000 '()))",
+            )
+            .unwrap();
+        assert_eq!(eval(&mut interp, "(equal? a b)"), "#t");
+        assert_eq!(eval(&mut interp, "(equal? a (list b))"), "#f");
+        assert_eq!(eval(&mut interp, "(equal? (cons 1 a) (cons 2 a))"), "#f");
+
+        let printed = interp.get("a").unwrap().to_string();
+        assert_eq!(printed.len(), 2 * 200001);
+        assert!(printed.starts_with("((((") && printed.ends_with("))))"));
+        assert_eq!(
+            eval(&mut interp, "(nest 2 '(1 \"x\" #t))"),
+            "(((1 \"x\" #t)))"
+        );
+
+        // and dropping the old values when they are redefined
+        assert_eq!(eval(&mut interp, "(define a '())"), "a");
+        assert_eq!(eval(&mut interp, "(define b '())"), "b");
+        drop(Value::list([Value::list([Value::nil()]), Value::Int(1)]));
+    }
+
+    #[test]
+    fn error_test() {
+        let mut interp = Interpreter::new();
+        assert_eq!(
+            interp.eval_str("(undefined 1)").err(),
+            Some(LispError::Unbound("undefined".to_string()))
+        );
+        assert!(matches!(
+            interp.eval_str("((lambda (x) x))"),
+            Err(LispError::Arity {
+                expected: 1,
+                found: 0,
+                ..
+            })
+        ));
+        assert!(matches!(
+            interp.eval_str("(cons 1 2)"),
+            Err(LispError::Type { .. })
+        ));
+        assert!(matches!(
+            interp.eval_str("(car '())"),
+            Err(LispError::Type { .. })
+        ));
+        assert!(matches!(
+            interp.eval_str("(1 2)"),
+            Err(LispError::Type { .. })
+        ));
+    }
+
+    #[test]
+    fn embed_test() {
+        let mut interp = Interpreter::new();
+        interp.define("limit", Value::Int(3));
+        interp.define_fn("count", |args| {
+            Ok(Value::Int(args[0].as_list()?.iter().count() as i64))
+        });
+        eval(&mut interp, "(define (allowed? xs) (<= (count xs) limit))");
+        assert_eq!(eval(&mut interp, "(allowed? '(a b))"), "#t");
+        assert_eq!(eval(&mut interp, "(allowed? '(a b c d))"), "#f");
+    }
+}
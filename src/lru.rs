@@ -0,0 +1,379 @@
+use crate::fourth::{List, NodeHandle};
+use std::borrow::Borrow;
+use std::cell::{Ref, RefMut};
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::rc::Rc;
+
+// a least recently used cache on top of fourth::List
+//
+// the list holds the entries in recency order, most recently used at the front, and the map finds the node
+// of a key through a NodeHandle. so a hit is a lookup plus a move_to_front, and making room is a pop_back:
+// everything is O(1). every key is stored once and shared through an Rc between the map and its list entry,
+// which also lets the cache hand the key back by value when an entry leaves
+pub struct LruCache<K, V> {
+    list: List<(Rc<K>, V)>,
+    map: HashMap<Key<K>, NodeHandle<(Rc<K>, V)>>,
+    capacity: usize,
+    on_evict: Option<Box<dyn FnMut(K, V)>>,
+    hits: u64,
+    misses: u64,
+    evictions: u64,
+}
+
+// a key of the map, shared with the list entry. HashMap is searched through Borrow, and an Rc<K> borrows
+// only as K, so a plain HashMap<Rc<K>, _> could not be searched with the &str of a String key. Key borrows
+// as a Query trait object instead, which any &Q that K borrows as can be wrapped into with Lookup. both
+// sides hash and compare as Q, so the map finds its keys exactly as HashMap<K, _> would with K: Borrow<Q>.
+// LinkedHashMap keys its map the same way
+pub(crate) struct Key<K>(pub(crate) Rc<K>);
+
+pub(crate) trait Query<Q: ?Sized> {
+    fn query(&self) -> &Q;
+}
+
+// the &Q a lookup is made with, to be passed to the map as &dyn Query<Q>
+pub(crate) struct Lookup<'a, Q: ?Sized>(pub(crate) &'a Q);
+
+impl<K: Borrow<Q>, Q: ?Sized> Query<Q> for Key<K> {
+    fn query(&self) -> &Q {
+        (*self.0).borrow()
+    }
+}
+
+impl<Q: ?Sized> Query<Q> for Lookup<'_, Q> {
+    fn query(&self) -> &Q {
+        self.0
+    }
+}
+
+impl<'a, K: Borrow<Q> + 'a, Q: ?Sized + 'a> Borrow<dyn Query<Q> + 'a> for Key<K> {
+    fn borrow(&self) -> &(dyn Query<Q> + 'a) {
+        self
+    }
+}
+
+impl<Q: Hash + ?Sized> Hash for dyn Query<Q> + '_ {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.query().hash(state)
+    }
+}
+
+impl<Q: PartialEq + ?Sized> PartialEq for dyn Query<Q> + '_ {
+    fn eq(&self, other: &Self) -> bool {
+        self.query() == other.query()
+    }
+}
+
+impl<Q: Eq + ?Sized> Eq for dyn Query<Q> + '_ {}
+
+impl<K: Hash> Hash for Key<K> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (*self.0).hash(state)
+    }
+}
+
+impl<K: PartialEq> PartialEq for Key<K> {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl<K: Eq> Eq for Key<K> {}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CacheStats {
+    // lookups through get and get_mut, peek is not counted
+    pub hits: u64,
+    pub misses: u64,
+    // entries pushed out by put or resize, not the ones taken out by pop_lru or remove
+    pub evictions: u64,
+}
+
+impl CacheStats {
+    // share of lookups that found their key, 0.0 before the first lookup
+    pub fn hit_ratio(&self) -> f64 {
+        match self.hits + self.misses {
+            0 => 0.0,
+            lookups => self.hits as f64 / lookups as f64,
+        }
+    }
+}
+
+impl<K: Hash + Eq, V> LruCache<K, V> {
+    // a cache of capacity 0 keeps nothing: every put is evicted straight away
+    pub fn new(capacity: usize) -> Self {
+        LruCache {
+            list: List::new(),
+            map: HashMap::new(),
+            capacity,
+            on_evict: None,
+            hits: 0,
+            misses: 0,
+            evictions: 0,
+        }
+    }
+
+    // called with every entry the cache drops to stay within its capacity
+    pub fn on_evict<F: FnMut(K, V) + 'static>(self, on_evict: F) -> Self {
+        LruCache {
+            on_evict: Some(Box::new(on_evict)),
+            ..self
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn contains<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.map.contains_key(&Lookup(key) as &dyn Query<Q>)
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.hits,
+            misses: self.misses,
+            evictions: self.evictions,
+        }
+    }
+
+    // marks the entry as the most recently used one
+    pub fn get<Q>(&mut self, key: &Q) -> Option<Ref<'_, V>>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let handle = self.touch(key)?;
+        // handles in the map always point into our own list, so they resolve
+        let entry = handle.get(&self.list).unwrap();
+        Some(Ref::map(entry, |(_, val)| val))
+    }
+
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<RefMut<'_, V>>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let handle = self.touch(key)?;
+        let entry = handle.get_mut(&mut self.list).unwrap();
+        Some(RefMut::map(entry, |(_, val)| val))
+    }
+
+    // looks at an entry without making it more recent or counting the lookup
+    pub fn peek<Q>(&self, key: &Q) -> Option<Ref<'_, V>>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let entry = self
+            .map
+            .get(&Lookup(key) as &dyn Query<Q>)?
+            .get(&self.list)
+            .unwrap();
+        Some(Ref::map(entry, |(_, val)| val))
+    }
+
+    // inserts or replaces the value of key and makes it the most recently used entry.
+    // returns the value it replaced, if any
+    pub fn put(&mut self, key: K, val: V) -> Option<V> {
+        if let Some(handle) = self.map.get(&Lookup(&key) as &dyn Query<K>) {
+            let handle = handle.clone();
+            let old = std::mem::replace(&mut handle.get_mut(&mut self.list).unwrap().1, val);
+            handle.move_to_front(&mut self.list).unwrap();
+            return Some(old);
+        }
+        let key = Rc::new(key);
+        let handle = self.list.push_front_handle((key.clone(), val));
+        self.map.insert(Key(key), handle);
+        self.shrink_to(self.capacity);
+        None
+    }
+
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let handle = self.map.remove(&Lookup(key) as &dyn Query<Q>)?;
+        let (_, val) = handle.remove(&mut self.list).unwrap();
+        Some(val)
+    }
+
+    // takes out the least recently used entry
+    pub fn pop_lru(&mut self) -> Option<(K, V)> {
+        let (key, val) = self.list.pop_back()?;
+        self.map.remove(&Lookup(&*key) as &dyn Query<K>);
+        // with the map's copy gone, the list entry held the last one
+        Some((Rc::try_unwrap(key).ok().unwrap(), val))
+    }
+
+    // shrinking evicts the least recently used entries that no longer fit
+    pub fn resize(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        self.shrink_to(capacity);
+    }
+
+    // most recently used first, reversible to start from the least recently used one.
+    // nothing here counts as a use
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = (Ref<'_, K>, Ref<'_, V>)> {
+        self.list
+            .iter()
+            .map(|entry| Ref::map_split(entry, |(key, val)| (&**key, val)))
+    }
+
+    fn touch<Q>(&mut self, key: &Q) -> Option<NodeHandle<(Rc<K>, V)>>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        match self.map.get(&Lookup(key) as &dyn Query<Q>) {
+            Some(handle) => {
+                self.hits += 1;
+                handle.move_to_front(&mut self.list).unwrap();
+                Some(handle.clone())
+            }
+            None => {
+                self.misses += 1;
+                None
+            }
+        }
+    }
+
+    fn shrink_to(&mut self, capacity: usize) {
+        while self.map.len() > capacity {
+            let (key, val) = self.pop_lru().unwrap();
+            self.evictions += 1;
+            if let Some(on_evict) = &mut self.on_evict {
+                on_evict(key, val);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::LruCache;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    fn keys(cache: &LruCache<i32, String>) -> Vec<i32> {
+        cache.iter().map(|(key, _)| *key).collect()
+    }
+
+    #[test]
+    fn basic_test() {
+        let mut cache = LruCache::new(3);
+        assert!(cache.is_empty());
+        assert_eq!(cache.put(1, "one".to_string()), None);
+        cache.put(2, "two".to_string());
+        cache.put(3, "three".to_string());
+        assert_eq!(keys(&cache), vec![3, 2, 1]);
+
+        // a hit moves the entry to the front, a peek does not
+        assert_eq!(cache.get(&1).as_deref().map(String::as_str), Some("one"));
+        assert_eq!(cache.peek(&2).as_deref().map(String::as_str), Some("two"));
+        assert_eq!(keys(&cache), vec![1, 3, 2]);
+
+        // so 2 is the one to go
+        cache.put(4, "four".to_string());
+        assert_eq!(keys(&cache), vec![4, 1, 3]);
+        assert!(!cache.contains(&2));
+        assert!(cache.get(&2).is_none());
+
+        // replacing a value counts as a use
+        assert_eq!(cache.put(3, "THREE".to_string()), Some("three".to_string()));
+        cache.get_mut(&1).unwrap().push('!');
+        assert_eq!(keys(&cache), vec![1, 3, 4]);
+        assert_eq!(
+            cache
+                .iter()
+                .rev()
+                .map(|(_, val)| val.clone())
+                .collect::<Vec<_>>(),
+            vec!["four", "THREE", "one!"]
+        );
+
+        assert_eq!(cache.remove(&3), Some("THREE".to_string()));
+        assert_eq!(cache.pop_lru(), Some((4, "four".to_string())));
+        assert_eq!(cache.len(), 1);
+        assert_eq!(cache.remove(&3), None);
+    }
+
+    #[test]
+    fn borrow_test() {
+        // String keys are looked up with a &str, as in a HashMap
+        let mut cache = LruCache::new(2);
+        cache.put("a".to_string(), 1);
+        cache.put("b".to_string(), 2);
+        assert!(cache.contains("a"));
+        assert_eq!(cache.peek("b").as_deref(), Some(&2));
+        *cache.get_mut("a").unwrap() += 10;
+        assert_eq!(cache.get("a").as_deref(), Some(&11));
+        assert_eq!(cache.remove("b"), Some(2));
+        assert!(cache.get("b").is_none());
+    }
+
+    #[test]
+    fn eviction_test() {
+        let evicted = Rc::new(RefCell::new(Vec::new()));
+        let log = evicted.clone();
+        let mut cache =
+            LruCache::new(2).on_evict(move |key, val| log.borrow_mut().push((key, val)));
+        for i in 0..5 {
+            cache.put(i, i * 10);
+        }
+        assert_eq!(*evicted.borrow(), vec![(0, 0), (1, 10), (2, 20)]);
+
+        // growing keeps everything, shrinking evicts from the least recently used end
+        cache.resize(4);
+        cache.put(5, 50);
+        cache.put(6, 60);
+        cache.get(&3);
+        cache.resize(2);
+        assert_eq!(cache.capacity(), 2);
+        assert_eq!(
+            cache.iter().map(|(key, _)| *key).collect::<Vec<_>>(),
+            vec![3, 6]
+        );
+        assert_eq!(evicted.borrow()[3..], [(4, 40), (5, 50)]);
+
+        // pop_lru is not an eviction
+        cache.pop_lru();
+        assert_eq!(evicted.borrow().len(), 5);
+        assert_eq!(cache.stats().evictions, 5);
+
+        let mut none = LruCache::new(0);
+        none.put("a", 1);
+        assert!(none.is_empty());
+    }
+
+    #[test]
+    fn stats_test() {
+        let mut cache = LruCache::new(100);
+        assert_eq!(cache.stats().hit_ratio(), 0.0);
+        for i in 0..100 {
+            cache.put(i, ());
+        }
+        // keys 0..200 twice: the first 100 always hit, the rest always miss and are never stored
+        for i in (0..200).chain(0..200) {
+            cache.get(&i);
+        }
+        cache.peek(&0);
+        let stats = cache.stats();
+        assert_eq!((stats.hits, stats.misses), (200, 200));
+        assert_eq!(stats.hit_ratio(), 0.5);
+    }
+}
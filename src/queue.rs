@@ -0,0 +1,354 @@
+use crate::third;
+use crate::third::List;
+use std::cell::{Cell, OnceCell};
+use std::rc::Rc;
+
+// banker's queue: a persistent FIFO queue made of two persistent stacks
+// elements are pushed onto back and popped from front, and when back grows longer than front,
+// front is replaced by front ++ reverse(back)
+//
+// doing that rotation eagerly is fine for an ephemeral queue, but a persistent queue can be forked:
+// take the version right before a rotation and push to it a thousand times, every fork pays O(n) again.
+// so the rotation is lazy and memoized instead, every version derived from the same rotation shares
+// one suspension, and the suspension is only evaluated one element at a time:
+//
+// rotate([], [y], a)         = y :: a
+// rotate(x :: f, y :: r, a)  = x :: rotate(f, r, y :: a)
+//
+// the reverse of back is accumulated into `a` step by step while front is walked, so by the time
+// a pop reaches the reversed part it has already been paid for by earlier pops
+type Front<T> = Rc<Susp<T>>;
+
+struct Susp<T> {
+    // OnceCell hands out &Forced<T> that lives as long as the suspension itself,
+    // so peek() can return a plain &T instead of a Ref<T>
+    forced: OnceCell<Forced<T>>,
+    pending: Cell<Option<Rotation<T>>>,
+}
+
+enum Forced<T> {
+    Cons(T, Front<T>),
+    // the rest of front is an ordinary strict list, which is what a finished rotation leaves behind
+    Rest(List<T>),
+}
+
+struct Rotation<T> {
+    front: Front<T>,
+    back: List<T>,
+    acc: List<T>,
+}
+
+impl<T: Clone> Susp<T> {
+    fn done(list: List<T>) -> Front<T> {
+        let forced = OnceCell::new();
+        let _ = forced.set(Forced::Rest(list));
+        Rc::new(Susp {
+            forced,
+            pending: Cell::new(None),
+        })
+    }
+
+    fn rotate(front: Front<T>, back: List<T>, acc: List<T>) -> Front<T> {
+        Rc::new(Susp {
+            forced: OnceCell::new(),
+            pending: Cell::new(Some(Rotation { front, back, acc })),
+        })
+    }
+
+    fn force(&self) -> &Forced<T> {
+        self.forced.get_or_init(|| {
+            let Rotation {
+                front,
+                mut back,
+                mut acc,
+            } = self
+                .pending
+                .take()
+                .expect("queue rotation was interrupted by a panic");
+            // back is always exactly one element longer than front when a rotation starts
+            let y = back.head().expect("rotation with empty back").clone();
+            match front.force() {
+                Forced::Cons(x, rest) => Forced::Cons(
+                    x.clone(),
+                    Susp::rotate(rest.clone(), back.tail(), acc.prepend(y)),
+                ),
+                Forced::Rest(list) => match list.head() {
+                    Some(x) => Forced::Cons(
+                        x.clone(),
+                        Susp::rotate(Susp::done(list.clone().tail()), back.tail(), acc.prepend(y)),
+                    ),
+                    None => Forced::Rest(acc.prepend(y)),
+                },
+            }
+        })
+    }
+}
+
+// forced cells link to each other through Rc, so dropping a long front would recurse once per element,
+// unlink them in a loop the same way third::List does
+impl<T> Drop for Susp<T> {
+    fn drop(&mut self) {
+        let mut next = match self.forced.take() {
+            Some(Forced::Cons(_, next)) => next,
+            _ => return,
+        };
+        while let Ok(mut susp) = Rc::try_unwrap(next) {
+            match susp.forced.take() {
+                Some(Forced::Cons(_, rest)) => next = rest,
+                _ => break,
+            }
+        }
+    }
+}
+
+pub struct PersistentQueue<T> {
+    front: Front<T>,
+    front_len: usize,
+    back: List<T>,
+    back_len: usize,
+}
+
+impl<T: Clone> PersistentQueue<T> {
+    pub fn new() -> Self {
+        PersistentQueue {
+            front: Susp::done(List::new()),
+            front_len: 0,
+            back: List::new(),
+            back_len: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.front_len + self.back_len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn push_back(&self, val: T) -> PersistentQueue<T> {
+        PersistentQueue::check(
+            self.front.clone(),
+            self.front_len,
+            self.back.clone().prepend(val),
+            self.back_len + 1,
+        )
+    }
+
+    // front is never shorter than back, so an empty front means an empty queue
+    pub fn peek(&self) -> Option<&T> {
+        match self.front.force() {
+            Forced::Cons(val, _) => Some(val),
+            Forced::Rest(list) => list.head(),
+        }
+    }
+
+    // returns the first element together with the queue without it, self is left untouched
+    pub fn pop_front(&self) -> Option<(&T, PersistentQueue<T>)> {
+        let (val, rest) = match self.front.force() {
+            Forced::Cons(val, rest) => (val, rest.clone()),
+            Forced::Rest(list) => (list.head()?, Susp::done(list.clone().tail())),
+        };
+        let queue =
+            PersistentQueue::check(rest, self.front_len - 1, self.back.clone(), self.back_len);
+        Some((val, queue))
+    }
+
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter {
+            front: FrontIter::Lazy(&self.front),
+            back: self.back.iter().collect(),
+        }
+    }
+
+    // restore the invariant back_len <= front_len by scheduling a rotation
+    fn check(front: Front<T>, front_len: usize, back: List<T>, back_len: usize) -> Self {
+        if back_len <= front_len {
+            PersistentQueue {
+                front,
+                front_len,
+                back,
+                back_len,
+            }
+        } else {
+            PersistentQueue {
+                front: Susp::rotate(front, back, List::new()),
+                front_len: front_len + back_len,
+                back: List::new(),
+                back_len: 0,
+            }
+        }
+    }
+}
+
+impl<T: Clone> Default for PersistentQueue<T> {
+    fn default() -> Self {
+        PersistentQueue::new()
+    }
+}
+
+impl<T> Clone for PersistentQueue<T> {
+    fn clone(&self) -> Self {
+        PersistentQueue {
+            front: self.front.clone(),
+            front_len: self.front_len,
+            back: self.back.clone(),
+            back_len: self.back_len,
+        }
+    }
+}
+
+// iterating forces front (which is memoized and shared with every other version),
+// then walks back in reverse, back is a stack so its elements are collected first
+pub struct Iter<'a, T> {
+    front: FrontIter<'a, T>,
+    back: Vec<&'a T>,
+}
+
+enum FrontIter<'a, T> {
+    Lazy(&'a Susp<T>),
+    Strict(third::Iter<'a, T>),
+}
+
+impl<'a, T: Clone> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+    fn next(&mut self) -> Option<Self::Item> {
+        if let FrontIter::Lazy(susp) = self.front {
+            match susp.force() {
+                Forced::Cons(val, rest) => {
+                    self.front = FrontIter::Lazy(rest);
+                    return Some(val);
+                }
+                Forced::Rest(list) => self.front = FrontIter::Strict(list.iter()),
+            }
+        }
+        if let FrontIter::Strict(iter) = &mut self.front {
+            if let Some(val) = iter.next() {
+                return Some(val);
+            }
+        }
+        self.back.pop()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PersistentQueue;
+    use std::collections::VecDeque;
+
+    // tiny deterministic generator, good enough to pick versions and operations
+    struct Lcg(u64);
+
+    impl Lcg {
+        fn next(&mut self, bound: usize) -> usize {
+            self.0 = self
+                .0
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            ((self.0 >> 33) as usize) % bound
+        }
+    }
+
+    fn contents(queue: &PersistentQueue<i32>) -> Vec<i32> {
+        queue.iter().cloned().collect()
+    }
+
+    #[test]
+    fn basic_test() {
+        let queue = PersistentQueue::new();
+        assert_eq!(queue.peek(), None);
+        assert!(queue.pop_front().is_none());
+
+        let queue = queue.push_back(1).push_back(2).push_back(3);
+        assert_eq!(queue.len(), 3);
+        assert_eq!(queue.peek(), Some(&1));
+
+        let (val, rest) = queue.pop_front().unwrap();
+        assert_eq!(val, &1);
+        assert_eq!(contents(&rest), vec![2, 3]);
+
+        // the old version is untouched
+        assert_eq!(contents(&queue), vec![1, 2, 3]);
+
+        let rest = rest.push_back(4);
+        let (val, rest) = rest.pop_front().unwrap();
+        assert_eq!(val, &2);
+        let (val, rest) = rest.pop_front().unwrap();
+        assert_eq!(val, &3);
+        let (val, rest) = rest.pop_front().unwrap();
+        assert_eq!(val, &4);
+        assert!(rest.is_empty());
+        assert!(rest.pop_front().is_none());
+    }
+
+    #[test]
+    fn fork_test() {
+        let mut rng = Lcg(42);
+        let mut versions = vec![(PersistentQueue::new(), VecDeque::new())];
+
+        for i in 0..5000 {
+            let (queue, mut model) = versions[rng.next(versions.len())].clone();
+            let (queue, model) = if rng.next(3) == 0 {
+                match queue.pop_front() {
+                    Some((val, rest)) => {
+                        assert_eq!(Some(*val), model.pop_front());
+                        (rest, model)
+                    }
+                    None => {
+                        assert!(model.is_empty());
+                        (queue, model)
+                    }
+                }
+            } else {
+                model.push_back(i);
+                (queue.push_back(i), model)
+            };
+            assert_eq!(queue.len(), model.len());
+            assert_eq!(queue.peek(), model.front());
+            versions.push((queue, model));
+        }
+
+        for (queue, model) in &versions {
+            assert_eq!(contents(queue), model.iter().cloned().collect::<Vec<_>>());
+        }
+    }
+
+    #[test]
+    fn rotation_fork_test() {
+        // front [0..8), back [8..15), the next push triggers a rotation
+        let mut base = PersistentQueue::new();
+        for i in 0..8 {
+            base = base.push_back(i);
+        }
+        let (_, mut base) = base.pop_front().unwrap();
+        for i in 8..15 {
+            base = base.push_back(i);
+        }
+
+        // every fork schedules its own rotation, none of them disturbs the others
+        let forks: Vec<_> = (100..110).map(|i| base.push_back(i)).collect();
+        for (i, fork) in forks.iter().enumerate() {
+            let mut expected: Vec<i32> = (1..15).collect();
+            expected.push(100 + i as i32);
+            assert_eq!(contents(fork), expected);
+        }
+        assert_eq!(contents(&base), (1..15).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn long_drop_test() {
+        let mut queue = PersistentQueue::new();
+        for i in 0..200_000 {
+            queue = queue.push_back(i);
+        }
+        // force the whole front so it becomes one long chain of memoized cells
+        assert_eq!(queue.iter().count(), 200_000);
+        let mut rest = queue.clone();
+        for _ in 0..100_000 {
+            rest = rest.pop_front().unwrap().1;
+        }
+        assert_eq!(rest.peek(), Some(&100_000));
+        drop(queue);
+        drop(rest);
+    }
+}
@@ -0,0 +1,343 @@
+use crate::third;
+use crate::third::List;
+use std::rc::Rc;
+
+// skew-binary random-access list (Okasaki), a sibling of third::List that indexes in O(log n)
+//
+// elements live in a spine of complete binary trees, whose sizes are skew-binary numbers 2^k - 1.
+// sizes strictly grow along the spine, except that the first two trees may have the same size:
+//
+// cons(x) when the first two trees are both of size w: x becomes the root over them, one tree of size 2w + 1
+// cons(x) otherwise: x is prepended as a new tree of size 1
+//
+// either way only O(1) nodes are created, and since the spine has O(log n) trees of depth O(log n),
+// get and set walk O(log n) nodes. trees and spine are shared through Rc, so every old version stays valid
+enum Tree<T> {
+    Leaf(T),
+    Node(T, Rc<Tree<T>>, Rc<Tree<T>>),
+}
+
+impl<T> Tree<T> {
+    fn root(&self) -> &T {
+        match self {
+            Tree::Leaf(val) => val,
+            Tree::Node(val, _, _) => val,
+        }
+    }
+
+    // elements are numbered in pre-order: root first, then the left subtree, then the right one
+    fn get(&self, size: usize, mut index: usize) -> &T {
+        let mut tree = self;
+        let mut size = size;
+        loop {
+            match tree {
+                Tree::Leaf(val) => return val,
+                Tree::Node(val, left, right) => {
+                    let half = size / 2;
+                    if index == 0 {
+                        return val;
+                    } else if index <= half {
+                        tree = left;
+                        index -= 1;
+                    } else {
+                        tree = right;
+                        index -= 1 + half;
+                    }
+                    size = half;
+                }
+            }
+        }
+    }
+}
+
+impl<T: Clone> Tree<T> {
+    // path copying: only the nodes from the root down to index are rebuilt, the rest is shared
+    fn set(&self, size: usize, index: usize, new: T) -> Tree<T> {
+        match self {
+            Tree::Leaf(_) => Tree::Leaf(new),
+            Tree::Node(val, left, right) => {
+                let half = size / 2;
+                if index == 0 {
+                    Tree::Node(new, left.clone(), right.clone())
+                } else if index <= half {
+                    let left = Rc::new(left.set(half, index - 1, new));
+                    Tree::Node(val.clone(), left, right.clone())
+                } else {
+                    let right = Rc::new(right.set(half, index - 1 - half, new));
+                    Tree::Node(val.clone(), left.clone(), right)
+                }
+            }
+        }
+    }
+}
+
+struct Digit<T> {
+    size: usize,
+    tree: Rc<Tree<T>>,
+}
+
+// derive(Clone) would require T: Clone, but cloning a digit only clones the Rc
+impl<T> Clone for Digit<T> {
+    fn clone(&self) -> Self {
+        Digit {
+            size: self.size,
+            tree: self.tree.clone(),
+        }
+    }
+}
+
+pub struct RandomAccessList<T> {
+    // the spine itself is a persistent third::List, cons and tail only touch its first two digits
+    digits: List<Digit<T>>,
+    len: usize,
+}
+
+impl<T> RandomAccessList<T> {
+    pub fn new() -> Self {
+        RandomAccessList {
+            digits: List::new(),
+            len: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn cons(&self, val: T) -> RandomAccessList<T> {
+        let rest = self.digits.clone().tail();
+        let digits = match (self.digits.head(), rest.head()) {
+            (Some(first), Some(second)) if first.size == second.size => {
+                rest.clone().tail().prepend(Digit {
+                    size: 1 + first.size * 2,
+                    tree: Rc::new(Tree::Node(val, first.tree.clone(), second.tree.clone())),
+                })
+            }
+            _ => self.digits.clone().prepend(Digit {
+                size: 1,
+                tree: Rc::new(Tree::Leaf(val)),
+            }),
+        };
+        RandomAccessList {
+            digits,
+            len: self.len + 1,
+        }
+    }
+
+    pub fn head(&self) -> Option<&T> {
+        self.digits.head().map(|digit| digit.tree.root())
+    }
+
+    // removing the root of the first tree leaves its two subtrees, which become the first two digits
+    pub fn tail(&self) -> RandomAccessList<T> {
+        let digit = match self.digits.head() {
+            None => return RandomAccessList::new(),
+            Some(digit) => digit,
+        };
+        let digits = match &*digit.tree {
+            Tree::Leaf(_) => self.digits.clone().tail(),
+            Tree::Node(_, left, right) => {
+                let half = digit.size / 2;
+                self.digits
+                    .clone()
+                    .tail()
+                    .prepend(Digit {
+                        size: half,
+                        tree: right.clone(),
+                    })
+                    .prepend(Digit {
+                        size: half,
+                        tree: left.clone(),
+                    })
+            }
+        };
+        RandomAccessList {
+            digits,
+            len: self.len - 1,
+        }
+    }
+
+    pub fn get(&self, mut index: usize) -> Option<&T> {
+        for digit in self.digits.iter() {
+            if index < digit.size {
+                return Some(digit.tree.get(digit.size, index));
+            }
+            index -= digit.size;
+        }
+        None
+    }
+
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter {
+            digits: self.digits.iter(),
+            stack: Vec::new(),
+        }
+    }
+}
+
+impl<T: Clone> RandomAccessList<T> {
+    // returns a new version with the element at index replaced, or None if index is out of bounds
+    // the digits in front of the changed tree are re-prepended, everything behind it is shared
+    pub fn set(&self, mut index: usize, val: T) -> Option<RandomAccessList<T>> {
+        let mut before = Vec::new();
+        let mut rest = self.digits.clone();
+        loop {
+            let digit = rest.head()?.clone();
+            rest = rest.tail();
+            if index < digit.size {
+                let tree = Rc::new(digit.tree.set(digit.size, index, val));
+                let mut digits = rest.prepend(Digit {
+                    size: digit.size,
+                    tree,
+                });
+                while let Some(digit) = before.pop() {
+                    digits = digits.prepend(digit);
+                }
+                return Some(RandomAccessList {
+                    digits,
+                    len: self.len,
+                });
+            }
+            index -= digit.size;
+            before.push(digit);
+        }
+    }
+}
+
+impl<T> Default for RandomAccessList<T> {
+    fn default() -> Self {
+        RandomAccessList::new()
+    }
+}
+
+impl<T> Clone for RandomAccessList<T> {
+    fn clone(&self) -> Self {
+        RandomAccessList {
+            digits: self.digits.clone(),
+            len: self.len,
+        }
+    }
+}
+
+impl<T: Clone> From<&List<T>> for RandomAccessList<T> {
+    fn from(list: &List<T>) -> Self {
+        let vals: Vec<&T> = list.iter().collect();
+        vals.into_iter()
+            .rev()
+            .fold(RandomAccessList::new(), |ral, val| ral.cons(val.clone()))
+    }
+}
+
+impl<T: Clone> From<&RandomAccessList<T>> for List<T> {
+    fn from(ral: &RandomAccessList<T>) -> Self {
+        let vals: Vec<&T> = ral.iter().collect();
+        vals.into_iter()
+            .rev()
+            .fold(List::new(), |mut list, val| list.prepend(val.clone()))
+    }
+}
+
+// pre-order walk over every tree of the spine, which is index order
+pub struct Iter<'a, T> {
+    digits: third::Iter<'a, Digit<T>>,
+    stack: Vec<&'a Tree<T>>,
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+    fn next(&mut self) -> Option<Self::Item> {
+        let tree = match self.stack.pop() {
+            Some(tree) => tree,
+            None => &*self.digits.next()?.tree,
+        };
+        match tree {
+            Tree::Leaf(val) => Some(val),
+            Tree::Node(val, left, right) => {
+                self.stack.push(right);
+                self.stack.push(left);
+                Some(val)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RandomAccessList;
+    use crate::third::List;
+
+    fn build(n: i32) -> RandomAccessList<i32> {
+        (0..n)
+            .rev()
+            .fold(RandomAccessList::new(), |ral, i| ral.cons(i))
+    }
+
+    #[test]
+    fn basic_test() {
+        let ral = RandomAccessList::new();
+        assert_eq!(ral.head(), None);
+        assert!(ral.tail().is_empty());
+
+        let ral = ral.cons(1).cons(2).cons(3);
+        assert_eq!(ral.len(), 3);
+        assert_eq!(ral.head(), Some(&3));
+
+        let tail = ral.tail();
+        assert_eq!(tail.head(), Some(&2));
+        assert_eq!(tail.tail().head(), Some(&1));
+        assert!(tail.tail().tail().is_empty());
+
+        // the old version is untouched
+        assert_eq!(ral.iter().collect::<Vec<_>>(), vec![&3, &2, &1]);
+    }
+
+    #[test]
+    fn get_set_test() {
+        for n in 0..70 {
+            let ral = build(n);
+            assert_eq!(ral.len(), n as usize);
+            for i in 0..n {
+                assert_eq!(ral.get(i as usize), Some(&i));
+            }
+            assert_eq!(ral.get(n as usize), None);
+            assert!(ral.set(n as usize, 0).is_none());
+
+            for i in 0..n {
+                let changed = ral.set(i as usize, -1).unwrap();
+                let mut expected: Vec<i32> = (0..n).collect();
+                expected[i as usize] = -1;
+                assert_eq!(changed.iter().cloned().collect::<Vec<_>>(), expected);
+                assert_eq!(ral.get(i as usize), Some(&i));
+            }
+        }
+    }
+
+    #[test]
+    fn tail_test() {
+        let mut ral = build(100);
+        for i in 0..100 {
+            assert_eq!(ral.head(), Some(&i));
+            assert_eq!(ral.get(99 - i as usize), Some(&99));
+            ral = ral.tail();
+        }
+        assert!(ral.is_empty());
+    }
+
+    #[test]
+    fn conversion_test() {
+        let list = List::new().prepend(1).prepend(2).prepend(3).prepend(4);
+        let ral = RandomAccessList::from(&list);
+        assert_eq!(
+            ral.iter().collect::<Vec<_>>(),
+            list.iter().collect::<Vec<_>>()
+        );
+        assert_eq!(ral.get(3), Some(&1));
+
+        let back = List::from(&ral.set(0, 5).unwrap());
+        assert_eq!(back.iter().collect::<Vec<_>>(), vec![&5, &3, &2, &1]);
+    }
+}
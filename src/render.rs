@@ -0,0 +1,375 @@
+use crate::third::{List, Node};
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::rc::Rc;
+
+// ascii diagrams of several third::Lists, drawn the way the comments in third.rs draw them by hand:
+//
+//      3
+//      |
+//      v
+// 4 -> 2 -> 1
+//
+// the longest list is the main row (ties go to the list given last, usually the newest version),
+// and every other list only adds the nodes it does not share: a branch row drawn above, with an arrow
+// down into the first shared node. branches can have branches of their own, and a second branch into
+// a node that already has one hangs off its arrow instead:
+//
+//   6
+//   |
+// 5 -+
+//   |
+//   v
+//   2 -> 1
+//
+// lists that share nothing are drawn one after another, separated by a blank line, and empty lists as []
+pub struct Renderer {
+    max_width: Option<usize>,
+    max_label: Option<usize>,
+}
+
+// one run of nodes drawn on a single line
+struct Row {
+    labels: Vec<String>,
+    // (index of the node in this row a branch joins, the branch's row)
+    branches: Vec<(usize, usize)>,
+}
+
+// a rendered row with everything above it, the bottom line being the row itself
+struct Canvas {
+    lines: Vec<Vec<char>>,
+    // start column of every label on the bottom line
+    cols: Vec<usize>,
+}
+
+impl Canvas {
+    fn width(&self) -> usize {
+        self.lines.iter().map(Vec::len).max().unwrap_or(0)
+    }
+}
+
+// a branch placed above a row: x may be negative until the whole block is shifted right
+struct Placed {
+    canvas: Canvas,
+    x: isize,
+    // the column of the node it joins, where its arrow runs
+    col: isize,
+    // joined from the side with " -+" rather than from straight above
+    tee: bool,
+}
+
+fn put(line: &mut Vec<char>, col: usize, text: &[char]) {
+    for (i, &c) in text.iter().enumerate() {
+        if line.len() <= col + i {
+            line.resize(col + i + 1, ' ');
+        }
+        if c != ' ' {
+            line[col + i] = c;
+        }
+    }
+}
+
+impl Renderer {
+    pub fn new() -> Self {
+        Renderer {
+            max_width: None,
+            max_label: None,
+        }
+    }
+
+    // lines longer than this are cut and end in "..."
+    pub fn max_width(self, max_width: usize) -> Self {
+        Renderer {
+            max_width: Some(max_width),
+            ..self
+        }
+    }
+
+    // elements printing longer than this are cut and end in "..."
+    pub fn max_label(self, max_label: usize) -> Self {
+        Renderer {
+            max_label: Some(max_label),
+            ..self
+        }
+    }
+
+    pub fn render<T: Display>(&self, lists: &[List<T>]) -> String {
+        let lens: Vec<usize> = lists.iter().map(|list| list.iter().count()).collect();
+        let mut order: Vec<usize> = (0..lists.len()).collect();
+        order.sort_by(|&a, &b| lens[b].cmp(&lens[a]).then(b.cmp(&a)));
+
+        // longest first, so the first list reaching a group of shared nodes draws the longest row,
+        // and every later list stops at the first node that is drawn already
+        let mut rows: Vec<Row> = Vec::new();
+        let mut roots = Vec::new();
+        let mut drawn: HashMap<*const Node<T>, (usize, usize)> = HashMap::new();
+        for &i in &order {
+            let id = rows.len();
+            let mut labels = Vec::new();
+            let mut link = lists[i].head.as_ref();
+            let mut join = None;
+            while let Some(node) = link {
+                if let Some(&at) = drawn.get(&Rc::as_ptr(node)) {
+                    join = Some(at);
+                    break;
+                }
+                drawn.insert(Rc::as_ptr(node), (id, labels.len()));
+                labels.push(self.label(&node.val));
+                link = node.next.as_ref();
+            }
+            if labels.is_empty() {
+                continue;
+            }
+            rows.push(Row {
+                labels,
+                branches: Vec::new(),
+            });
+            match join {
+                Some((row, index)) => rows[row].branches.push((index, id)),
+                None => roots.push(id),
+            }
+        }
+
+        let mut blocks: Vec<Vec<String>> = roots
+            .into_iter()
+            .map(|root| {
+                layout(&rows, root)
+                    .lines
+                    .iter()
+                    .map(|line| self.fit(line))
+                    .collect()
+            })
+            .collect();
+        if lens.contains(&0) || lists.is_empty() {
+            blocks.push(vec!["[]".to_string()]);
+        }
+        blocks
+            .into_iter()
+            .map(|lines| lines.join("\n"))
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    }
+
+    fn label<T: Display>(&self, val: &T) -> String {
+        let label: String = val
+            .to_string()
+            .chars()
+            .map(|c| if c.is_control() { '?' } else { c })
+            .collect();
+        match self.max_label {
+            Some(max) if label.chars().count() > max => {
+                let mut cut: String = label.chars().take(max.saturating_sub(3)).collect();
+                cut.push_str("...");
+                cut
+            }
+            _ => label,
+        }
+    }
+
+    fn fit(&self, line: &[char]) -> String {
+        let line: String = line.iter().collect::<String>().trim_end().to_string();
+        match self.max_width {
+            Some(max) if line.chars().count() > max => {
+                let mut cut: String = line.chars().take(max.saturating_sub(3)).collect();
+                cut.push_str("...");
+                cut
+            }
+            _ => line,
+        }
+    }
+}
+
+impl Default for Renderer {
+    fn default() -> Self {
+        Renderer::new()
+    }
+}
+
+pub fn render<T: Display>(lists: &[List<T>]) -> String {
+    Renderer::new().render(lists)
+}
+
+fn layout(rows: &[Row], id: usize) -> Canvas {
+    let row = &rows[id];
+    let mut bottom = Vec::new();
+    let mut cols = Vec::new();
+    for (i, label) in row.labels.iter().enumerate() {
+        if i > 0 {
+            bottom.extend(" -> ".chars());
+        }
+        cols.push(bottom.len());
+        bottom.extend(label.chars());
+    }
+
+    // the branch joining furthest right goes on top, so every arrow only passes branches to its left
+    let mut branches = row.branches.clone();
+    branches.sort_by_key(|&(index, _)| std::cmp::Reverse(index));
+
+    let mut placed: Vec<Placed> = Vec::new();
+    for (index, branch) in branches {
+        let canvas = layout(rows, branch);
+        let col = cols[index] as isize;
+        let width = canvas.width() as isize;
+        let anchor = *canvas.cols.last().unwrap() as isize;
+        let bottom_len = canvas.lines.last().unwrap().len() as isize;
+
+        // straight above the node, if that is its first branch and it stays clear of the arrows passing by
+        let x = col - anchor;
+        let clear = placed.iter().all(|p| p.col != col && x + width < p.col - 1);
+        let (x, tee) = match clear {
+            true => (x, false),
+            false => (col - (bottom_len + 2).max(width + 1), true),
+        };
+        placed.push(Placed {
+            canvas,
+            x,
+            col,
+            tee,
+        });
+    }
+
+    let shift = placed.iter().map(|p| -p.x).max().unwrap_or(0).max(0);
+    let mut lines = Vec::new();
+    let mut arrows: Vec<usize> = Vec::new();
+    for p in &placed {
+        let x = (p.x + shift) as usize;
+        let col = (p.col + shift) as usize;
+        let last = p.canvas.lines.len() - 1;
+        for (k, src) in p.canvas.lines.iter().enumerate() {
+            let mut line = Vec::new();
+            for &arrow in &arrows {
+                put(&mut line, arrow, &['|']);
+            }
+            put(&mut line, x, src);
+            if k == last && p.tee {
+                let dashes = vec!['-'; col - (x + src.len() + 1)];
+                put(&mut line, x + src.len() + 1, &dashes);
+                line[col] = '+';
+            }
+            lines.push(line);
+        }
+        if !arrows.contains(&col) {
+            arrows.push(col);
+        }
+        let mut line = Vec::new();
+        for &arrow in &arrows {
+            put(&mut line, arrow, &['|']);
+        }
+        lines.push(line);
+    }
+    if !arrows.is_empty() {
+        let mut line = Vec::new();
+        for &arrow in &arrows {
+            put(&mut line, arrow, &['v']);
+        }
+        lines.push(line);
+    }
+
+    let mut line = Vec::new();
+    put(&mut line, shift as usize, &bottom);
+    lines.push(line);
+    Canvas {
+        lines,
+        cols: cols.into_iter().map(|c| c + shift as usize).collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{render, Renderer};
+    use crate::third::List;
+
+    #[test]
+    fn third_test() {
+        // the diagrams from third.rs basic_test
+        let mut list = List::new().prepend(1).prepend(2).prepend(3);
+        assert_eq!(render(std::slice::from_ref(&list)), "3 -> 2 -> 1");
+
+        let branch = list.tail().prepend(4);
+        assert_eq!(
+            render(&[list.clone(), branch]),
+            "     3\n\
+             \x20    |\n\
+             \x20    v\n\
+             4 -> 2 -> 1"
+        );
+
+        assert_eq!(render(&[list.clone(), list.tail()]), "3 -> 2 -> 1");
+        assert_eq!(render::<i32>(&[]), "[]");
+        assert_eq!(render(&[List::<i32>::new()]), "[]");
+    }
+
+    #[test]
+    fn branches_test() {
+        let mut base = List::new().prepend(1).prepend(2);
+        let mut main = base.prepend(3).prepend(4);
+        let lists = [
+            main.clone(),
+            base.prepend(5),
+            base.prepend(6),
+            main.tail().prepend(7).prepend(8),
+        ];
+        // the second branch into 2 hangs off the arrow of the first
+        assert_eq!(
+            render(&lists),
+            [
+                "               6",
+                "               |",
+                "            5 -+",
+                "               |",
+                "          4    |",
+                "          |    |",
+                "          v    v",
+                "8 -> 7 -> 3 -> 2 -> 1",
+            ]
+            .join("\n")
+        );
+    }
+
+    #[test]
+    fn nested_test() {
+        let main = (1..=5).fold(List::new(), |mut list, i| list.prepend(i));
+        let mut last = main.clone();
+        while last.tail().head().is_some() {
+            last = last.tail();
+        }
+        let mut branch = last.prepend(10).prepend(20);
+        let twig = branch.tail().prepend(30);
+        let mut separate = List::new().prepend("x");
+
+        assert_eq!(
+            render(&[main, branch, twig, List::new()]),
+            [
+                "                    20",
+                "                    |",
+                "                    v",
+                "              30 -> 10",
+                "                    |",
+                "                    v",
+                "5 -> 4 -> 3 -> 2 -> 1",
+                "",
+                "[]",
+            ]
+            .join("\n")
+        );
+        assert_eq!(render(&[separate.clone(), separate.prepend("y")]), "y -> x");
+        assert_eq!(
+            render(&[separate.clone(), List::new().prepend("x")]),
+            "x\n\nx"
+        );
+    }
+
+    #[test]
+    fn truncate_test() {
+        let words = List::new()
+            .prepend("short".to_string())
+            .prepend("a rather long element".to_string());
+        let renderer = Renderer::new().max_label(8);
+        assert_eq!(renderer.render(&[words]), "a rat... -> short");
+
+        let long = (0..100).fold(List::new(), |mut list, i| list.prepend(i));
+        let text = Renderer::new().max_width(20).render(&[long]);
+        assert_eq!(text, "99 -> 98 -> 97 ->...");
+        assert!(text.lines().all(|line| line.len() <= 20));
+    }
+}
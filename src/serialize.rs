@@ -0,0 +1,629 @@
+use crate::third::{List, Node};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::rc::Rc;
+
+// sharing-preserving serialization for a set of third::Lists
+//
+// lists that share tails form a DAG of nodes. writing them one by one would duplicate every shared node,
+// and reading them back would give independent copies. instead all lists are written together as one node table:
+// every distinct node (told apart by its Rc address) is written once, tail before head, and refers to its next node
+// by index. the lists themselves are just indices of their head nodes.
+//
+// binary format (integers are LEB128 varints, references are index + 1 with 0 meaning nil):
+//
+//   "TMLD" version:u8 node_count { next element }* root_count { root }*
+//
+// every element starts with a type tag, so a table written with one element type cannot be read as another.
+// text format, one line per node or root:
+//
+//   too-many-lists-dag 1
+//   node 0 nil 1
+//   node 1 #0 2
+//   root #1
+//   root nil
+const MAGIC: &[u8; 4] = b"TMLD";
+const TEXT_MAGIC: &str = "too-many-lists-dag";
+const VERSION: u32 = 1;
+
+const TAG_INT: u8 = 1;
+const TAG_UINT: u8 = 2;
+const TAG_BOOL: u8 = 3;
+const TAG_STR: u8 = 4;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DecodeError {
+    BadMagic,
+    UnsupportedVersion(u32),
+    UnexpectedEof,
+    TrailingBytes,
+    // a node or root refers to a node index that is not in the table
+    DanglingReference(usize),
+    // following next from this node comes back to it, which no third::List can do
+    Cycle(usize),
+    TypeMismatch { expected: &'static str, tag: u8 },
+    OutOfRange,
+    InvalidUtf8,
+    Syntax { line: usize, message: String },
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::BadMagic => write!(f, "not a list table"),
+            DecodeError::UnsupportedVersion(v) => write!(f, "unsupported format version {}", v),
+            DecodeError::UnexpectedEof => write!(f, "unexpected end of input"),
+            DecodeError::TrailingBytes => write!(f, "trailing bytes after the table"),
+            DecodeError::DanglingReference(i) => write!(f, "reference to missing node {}", i),
+            DecodeError::Cycle(i) => write!(f, "node {} is part of a cycle", i),
+            DecodeError::TypeMismatch { expected, tag } => {
+                write!(f, "expected {}, found element tag {}", expected, tag)
+            }
+            DecodeError::OutOfRange => write!(f, "number out of range"),
+            DecodeError::InvalidUtf8 => write!(f, "invalid utf-8 in string"),
+            DecodeError::Syntax { line, message } => write!(f, "line {}: {}", line, message),
+        }
+    }
+}
+
+impl Error for DecodeError {}
+
+// element types that can be stored in a node table
+pub trait Element: Sized {
+    fn encode(&self, out: &mut Vec<u8>);
+    fn decode(input: &mut &[u8]) -> Result<Self, DecodeError>;
+    fn write_text(&self, out: &mut String);
+    fn read_text(text: &str) -> Result<Self, String>;
+}
+
+fn write_varint(out: &mut Vec<u8>, mut n: u64) {
+    loop {
+        let byte = (n & 0x7f) as u8;
+        n >>= 7;
+        if n == 0 {
+            out.push(byte);
+            return;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+fn read_byte(input: &mut &[u8]) -> Result<u8, DecodeError> {
+    let (&byte, rest) = input.split_first().ok_or(DecodeError::UnexpectedEof)?;
+    *input = rest;
+    Ok(byte)
+}
+
+fn read_varint(input: &mut &[u8]) -> Result<u64, DecodeError> {
+    let mut n = 0u64;
+    for shift in (0..64).step_by(7) {
+        let byte = read_byte(input)?;
+        // the tenth byte only has room for the top bit
+        if shift == 63 && byte & 0x7f > 1 {
+            return Err(DecodeError::OutOfRange);
+        }
+        n |= ((byte & 0x7f) as u64) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(n);
+        }
+    }
+    Err(DecodeError::OutOfRange)
+}
+
+fn read_tag(input: &mut &[u8], expected: u8, name: &'static str) -> Result<(), DecodeError> {
+    match read_byte(input)? {
+        tag if tag == expected => Ok(()),
+        tag => Err(DecodeError::TypeMismatch {
+            expected: name,
+            tag,
+        }),
+    }
+}
+
+macro_rules! signed_element {
+    ($($t:ty),*) => {$(
+        impl Element for $t {
+            fn encode(&self, out: &mut Vec<u8>) {
+                out.push(TAG_INT);
+                // zigzag, so small negative numbers stay short
+                let n = *self as i64;
+                write_varint(out, ((n << 1) ^ (n >> 63)) as u64);
+            }
+
+            fn decode(input: &mut &[u8]) -> Result<Self, DecodeError> {
+                read_tag(input, TAG_INT, "signed integer")?;
+                let n = read_varint(input)?;
+                let n = ((n >> 1) as i64) ^ -((n & 1) as i64);
+                <$t>::try_from(n).map_err(|_| DecodeError::OutOfRange)
+            }
+
+            fn write_text(&self, out: &mut String) {
+                out.push_str(&self.to_string());
+            }
+
+            fn read_text(text: &str) -> Result<Self, String> {
+                text.parse().map_err(|err| format!("{}: {:?}", err, text))
+            }
+        }
+    )*};
+}
+
+macro_rules! unsigned_element {
+    ($($t:ty),*) => {$(
+        impl Element for $t {
+            fn encode(&self, out: &mut Vec<u8>) {
+                out.push(TAG_UINT);
+                write_varint(out, *self as u64);
+            }
+
+            fn decode(input: &mut &[u8]) -> Result<Self, DecodeError> {
+                read_tag(input, TAG_UINT, "unsigned integer")?;
+                <$t>::try_from(read_varint(input)?).map_err(|_| DecodeError::OutOfRange)
+            }
+
+            fn write_text(&self, out: &mut String) {
+                out.push_str(&self.to_string());
+            }
+
+            fn read_text(text: &str) -> Result<Self, String> {
+                text.parse().map_err(|err| format!("{}: {:?}", err, text))
+            }
+        }
+    )*};
+}
+
+signed_element!(i8, i16, i32, i64, isize);
+unsigned_element!(u8, u16, u32, u64, usize);
+
+impl Element for bool {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.push(TAG_BOOL);
+        out.push(*self as u8);
+    }
+
+    fn decode(input: &mut &[u8]) -> Result<Self, DecodeError> {
+        read_tag(input, TAG_BOOL, "bool")?;
+        match read_byte(input)? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(DecodeError::OutOfRange),
+        }
+    }
+
+    fn write_text(&self, out: &mut String) {
+        out.push_str(if *self { "true" } else { "false" });
+    }
+
+    fn read_text(text: &str) -> Result<Self, String> {
+        text.parse()
+            .map_err(|_| format!("expected true or false: {:?}", text))
+    }
+}
+
+impl Element for String {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.push(TAG_STR);
+        write_varint(out, self.len() as u64);
+        out.extend_from_slice(self.as_bytes());
+    }
+
+    fn decode(input: &mut &[u8]) -> Result<Self, DecodeError> {
+        read_tag(input, TAG_STR, "string")?;
+        let len = usize::try_from(read_varint(input)?).map_err(|_| DecodeError::OutOfRange)?;
+        if input.len() < len {
+            return Err(DecodeError::UnexpectedEof);
+        }
+        let (bytes, rest) = input.split_at(len);
+        *input = rest;
+        String::from_utf8(bytes.to_vec()).map_err(|_| DecodeError::InvalidUtf8)
+    }
+
+    // the same escapes as Debug, so the text stays on one line
+    fn write_text(&self, out: &mut String) {
+        out.push_str(&format!("{:?}", self));
+    }
+
+    fn read_text(text: &str) -> Result<Self, String> {
+        let inner = text
+            .strip_prefix('"')
+            .and_then(|text| text.strip_suffix('"'))
+            .ok_or_else(|| format!("expected a quoted string: {}", text))?;
+        let mut s = String::new();
+        let mut chars = inner.chars();
+        while let Some(c) = chars.next() {
+            if c != '\\' {
+                s.push(c);
+                continue;
+            }
+            match chars.next() {
+                Some('n') => s.push('\n'),
+                Some('r') => s.push('\r'),
+                Some('t') => s.push('\t'),
+                Some('0') => s.push('\0'),
+                Some(c @ ('\\' | '"' | '\'')) => s.push(c),
+                Some('u') => {
+                    let rest: String = chars.by_ref().take_while(|&c| c != '}').collect();
+                    let c = rest
+                        .strip_prefix('{')
+                        .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                        .and_then(char::from_u32)
+                        .ok_or_else(|| format!("bad unicode escape in {}", text))?;
+                    s.push(c);
+                }
+                _ => return Err(format!("bad escape in {}", text)),
+            }
+        }
+        Ok(s)
+    }
+}
+
+// every distinct node of the lists, tail before head, and the index of each list's head node
+struct Table<'a, T> {
+    nodes: Vec<&'a Node<T>>,
+    next: Vec<Option<usize>>,
+    roots: Vec<Option<usize>>,
+}
+
+fn table<T>(lists: &[List<T>]) -> Table<'_, T> {
+    let mut ids: HashMap<*const Node<T>, usize> = HashMap::new();
+    let mut table = Table {
+        nodes: Vec::new(),
+        next: Vec::new(),
+        roots: Vec::new(),
+    };
+    for list in lists {
+        // walk until a node that is already in the table, the rest of the list is in there too
+        let mut fresh = Vec::new();
+        let mut link = list.head.as_ref();
+        while let Some(node) = link {
+            if ids.contains_key(&Rc::as_ptr(node)) {
+                break;
+            }
+            fresh.push(node);
+            link = node.next.as_ref();
+        }
+        for node in fresh.into_iter().rev() {
+            let next = node.next.as_ref().map(|next| ids[&Rc::as_ptr(next)]);
+            ids.insert(Rc::as_ptr(node), table.nodes.len());
+            table.nodes.push(node);
+            table.next.push(next);
+        }
+        table
+            .roots
+            .push(list.head.as_ref().map(|head| ids[&Rc::as_ptr(head)]));
+    }
+    table
+}
+
+// rebuilds the nodes from a table that may come from anywhere, so references can point in any direction
+// and have to be checked for cycles and dangling indices before anything is linked.
+// every node built is kept as the head of a List: the table may leave long chains without a root, or be
+// rejected after they were built, and dropping them as Lists frees them without recursing down the chain
+fn build<T>(
+    next: Vec<Option<usize>>,
+    vals: Vec<T>,
+    roots: Vec<Option<usize>>,
+) -> Result<Vec<List<T>>, DecodeError> {
+    const UNVISITED: u8 = 0;
+    const VISITING: u8 = 1;
+    const BUILT: u8 = 2;
+
+    let count = next.len();
+    let mut state = vec![UNVISITED; count];
+    let mut built: Vec<List<T>> = (0..count).map(|_| List::new()).collect();
+    let mut vals: Vec<Option<T>> = vals.into_iter().map(Some).collect();
+
+    for start in 0..count {
+        // follow next pointers until a built node or the end of the list,
+        // then build the path backwards so every node's next already exists
+        let mut path = Vec::new();
+        let mut id = start;
+        while state[id] != BUILT {
+            if state[id] == VISITING {
+                return Err(DecodeError::Cycle(id));
+            }
+            state[id] = VISITING;
+            path.push(id);
+            match next[id] {
+                None => break,
+                Some(target) if target >= count => {
+                    return Err(DecodeError::DanglingReference(target))
+                }
+                Some(target) => id = target,
+            }
+        }
+        for id in path.into_iter().rev() {
+            built[id].head = Some(Rc::new(Node {
+                val: vals[id].take().unwrap(),
+                next: next[id].and_then(|target| built[target].head.clone()),
+            }));
+            state[id] = BUILT;
+        }
+    }
+
+    roots
+        .into_iter()
+        .map(|root| match root {
+            None => Ok(List::new()),
+            Some(id) if id >= count => Err(DecodeError::DanglingReference(id)),
+            Some(id) => Ok(List {
+                head: built[id].head.clone(),
+            }),
+        })
+        .collect()
+}
+
+fn encode_ref(out: &mut Vec<u8>, id: Option<usize>) {
+    write_varint(out, id.map_or(0, |id| id as u64 + 1));
+}
+
+fn decode_ref(input: &mut &[u8]) -> Result<Option<usize>, DecodeError> {
+    match read_varint(input)? {
+        0 => Ok(None),
+        n => usize::try_from(n - 1)
+            .map(Some)
+            .map_err(|_| DecodeError::OutOfRange),
+    }
+}
+
+pub fn to_bytes<T: Element>(lists: &[List<T>]) -> Vec<u8> {
+    let table = table(lists);
+    let mut out = MAGIC.to_vec();
+    out.push(VERSION as u8);
+    write_varint(&mut out, table.nodes.len() as u64);
+    for (node, next) in table.nodes.iter().zip(&table.next) {
+        encode_ref(&mut out, *next);
+        node.val.encode(&mut out);
+    }
+    write_varint(&mut out, table.roots.len() as u64);
+    for root in &table.roots {
+        encode_ref(&mut out, *root);
+    }
+    out
+}
+
+pub fn from_bytes<T: Element>(mut input: &[u8]) -> Result<Vec<List<T>>, DecodeError> {
+    let input = &mut input;
+    if input.len() < MAGIC.len() || &input[..MAGIC.len()] != MAGIC {
+        return Err(DecodeError::BadMagic);
+    }
+    *input = &input[MAGIC.len()..];
+    let version = read_byte(input)? as u32;
+    if version != VERSION {
+        return Err(DecodeError::UnsupportedVersion(version));
+    }
+
+    let count = read_varint(input)?;
+    let mut next = Vec::new();
+    let mut vals = Vec::new();
+    for _ in 0..count {
+        next.push(decode_ref(input)?);
+        vals.push(T::decode(input)?);
+    }
+    let mut roots = Vec::new();
+    for _ in 0..read_varint(input)? {
+        roots.push(decode_ref(input)?);
+    }
+    if !input.is_empty() {
+        return Err(DecodeError::TrailingBytes);
+    }
+    build(next, vals, roots)
+}
+
+fn text_ref(id: Option<usize>) -> String {
+    match id {
+        Some(id) => format!("#{}", id),
+        None => "nil".to_string(),
+    }
+}
+
+pub fn to_text<T: Element>(lists: &[List<T>]) -> String {
+    let table = table(lists);
+    let mut out = format!("{} {}\n", TEXT_MAGIC, VERSION);
+    for (id, (node, next)) in table.nodes.iter().zip(&table.next).enumerate() {
+        out.push_str(&format!("node {} {} ", id, text_ref(*next)));
+        node.val.write_text(&mut out);
+        out.push('\n');
+    }
+    for root in &table.roots {
+        out.push_str(&format!("root {}\n", text_ref(*root)));
+    }
+    out
+}
+
+pub fn from_text<T: Element>(text: &str) -> Result<Vec<List<T>>, DecodeError> {
+    let mut lines = text
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty());
+
+    let (_, header) = lines.next().ok_or(DecodeError::UnexpectedEof)?;
+    let version = match header.split_once(' ') {
+        Some((TEXT_MAGIC, version)) => version.parse().map_err(|_| DecodeError::BadMagic)?,
+        _ => return Err(DecodeError::BadMagic),
+    };
+    if version != VERSION {
+        return Err(DecodeError::UnsupportedVersion(version));
+    }
+
+    let mut next = Vec::new();
+    let mut vals = Vec::new();
+    let mut roots = Vec::new();
+    for (line, content) in lines {
+        let syntax = |message: String| DecodeError::Syntax { line, message };
+        let parse_ref = |word: &str| match word {
+            "nil" => Ok(None),
+            _ => word
+                .strip_prefix('#')
+                .and_then(|id| id.parse().ok())
+                .map(Some)
+                .ok_or_else(|| syntax(format!("expected #index or nil, found {:?}", word))),
+        };
+
+        let mut words = content.splitn(4, ' ');
+        match words.next() {
+            Some("node") if roots.is_empty() => {
+                let id = words.next().and_then(|id| id.parse::<usize>().ok());
+                if id != Some(next.len()) {
+                    return Err(syntax(format!("expected node {}", next.len())));
+                }
+                next.push(parse_ref(words.next().unwrap_or(""))?);
+                vals.push(T::read_text(words.next().unwrap_or("")).map_err(syntax)?);
+            }
+            Some("root") => roots.push(parse_ref(words.next().unwrap_or(""))?),
+            _ => return Err(syntax(format!("unexpected line {:?}", content))),
+        }
+    }
+    build(next, vals, roots)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        encode_ref, from_bytes, from_text, read_varint, to_bytes, to_text, write_varint,
+        DecodeError,
+    };
+    use crate::third::List;
+
+    // 3 -> 2 -> 1, 4 -> 2 -> 1, and 2 -> 1 itself, plus an empty list
+    fn shared() -> Vec<List<i64>> {
+        let mut base = List::new().prepend(1).prepend(2);
+        vec![base.prepend(3), base.prepend(4), base, List::new()]
+    }
+
+    fn check_sharing(lists: &[List<i64>]) {
+        let values: Vec<Vec<i64>> = lists.iter().map(|l| l.iter().cloned().collect()).collect();
+        assert_eq!(
+            values,
+            vec![vec![3, 2, 1], vec![4, 2, 1], vec![2, 1], vec![]]
+        );
+        assert_eq!(List::shared_suffix_len(&lists[0], &lists[1]), 2);
+        assert!(lists[0].clone().tail().ptr_eq(&lists[2]));
+        assert!(lists[1].clone().tail().ptr_eq(&lists[2]));
+    }
+
+    #[test]
+    fn bytes_round_trip_test() {
+        let bytes = to_bytes(&shared());
+        check_sharing(&from_bytes(&bytes).unwrap());
+
+        // shared nodes are written once: four distinct nodes
+        assert_eq!(&bytes[..5], b"TMLD\x01");
+        assert_eq!(bytes[5], 4);
+    }
+
+    #[test]
+    fn text_round_trip_test() {
+        let text = to_text(&shared());
+        assert_eq!(
+            text,
+            "too-many-lists-dag 1\n\
+             node 0 nil 1\n\
+             node 1 #0 2\n\
+             node 2 #1 3\n\
+             node 3 #1 4\n\
+             root #2\n\
+             root #3\n\
+             root #1\n\
+             root nil\n"
+        );
+        check_sharing(&from_text(&text).unwrap());
+    }
+
+    #[test]
+    fn string_test() {
+        let mut base = List::new().prepend("tail with \"quotes\"\n".to_string());
+        let lists = vec![base.prepend("é ✓ \u{1}".to_string()), base];
+        let text = to_text(&lists);
+        let mut decoded: Vec<List<String>> = from_text(&text).unwrap();
+        assert_eq!(decoded[0].head(), lists[0].head());
+        assert!(decoded[0].tail().ptr_eq(&decoded[1]));
+
+        let decoded: Vec<List<String>> = from_bytes(&to_bytes(&lists)).unwrap();
+        assert_eq!(decoded[1].head(), lists[1].head());
+    }
+
+    #[test]
+    fn reject_test() {
+        let cycle = "too-many-lists-dag 1\nnode 0 #1 1\nnode 1 #0 2\nroot #0\n";
+        assert_eq!(from_text::<i64>(cycle).err(), Some(DecodeError::Cycle(0)));
+
+        let self_loop = "too-many-lists-dag 1\nnode 0 #0 1\nroot nil\n";
+        assert_eq!(
+            from_text::<i64>(self_loop).err(),
+            Some(DecodeError::Cycle(0))
+        );
+
+        let dangling = "too-many-lists-dag 1\nnode 0 #7 1\nroot #0\n";
+        assert_eq!(
+            from_text::<i64>(dangling).err(),
+            Some(DecodeError::DanglingReference(7))
+        );
+
+        let dangling_root = "too-many-lists-dag 1\nnode 0 nil 1\nroot #1\n";
+        assert_eq!(
+            from_text::<i64>(dangling_root).err(),
+            Some(DecodeError::DanglingReference(1))
+        );
+
+        let future = "too-many-lists-dag 2\n";
+        assert_eq!(
+            from_text::<i64>(future).err(),
+            Some(DecodeError::UnsupportedVersion(2))
+        );
+
+        let bytes = to_bytes(&shared());
+        assert_eq!(
+            from_bytes::<i64>(b"nope").err(),
+            Some(DecodeError::BadMagic)
+        );
+        assert_eq!(
+            from_bytes::<i64>(&bytes[..bytes.len() - 1]).err(),
+            Some(DecodeError::UnexpectedEof)
+        );
+        assert!(matches!(
+            from_bytes::<String>(&bytes),
+            Err(DecodeError::TypeMismatch { .. })
+        ));
+        assert!(matches!(
+            from_text::<i64>("too-many-lists-dag 1\nnode 0 nil x\n"),
+            Err(DecodeError::Syntax { line: 2, .. })
+        ));
+    }
+
+    #[test]
+    fn hostile_input_test() {
+        // a long chain that no root keeps, or that is built before a bad root is found,
+        // is freed without recursing once per node
+        let len = 300_000u32;
+        let list = (0..len).fold(List::new(), |mut list, i| list.prepend(i));
+        let bytes = to_bytes(std::slice::from_ref(&list));
+        let mut roots = Vec::new();
+        write_varint(&mut roots, 1);
+        encode_ref(&mut roots, Some(len as usize - 1));
+        let body = &bytes[..bytes.len() - roots.len()];
+
+        let mut unrooted = body.to_vec();
+        write_varint(&mut unrooted, 0);
+        assert_eq!(from_bytes::<u32>(&unrooted).unwrap().len(), 0);
+        let mut dangling = body.to_vec();
+        write_varint(&mut dangling, 1);
+        encode_ref(&mut dangling, Some(len as usize));
+        assert_eq!(
+            from_bytes::<u32>(&dangling).err(),
+            Some(DecodeError::DanglingReference(len as usize))
+        );
+
+        // varints past 64 bits
+        let mut max = vec![0xff; 9];
+        max.push(0x01);
+        assert_eq!(read_varint(&mut &max[..]), Ok(u64::MAX));
+        let mut over = vec![0xff; 9];
+        over.push(0x02);
+        assert_eq!(read_varint(&mut &over[..]), Err(DecodeError::OutOfRange));
+        over[9] = 0x81;
+        assert_eq!(read_varint(&mut &over[..]), Err(DecodeError::OutOfRange));
+    }
+}
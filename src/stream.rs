@@ -0,0 +1,367 @@
+use crate::third::List;
+use std::cell::{Cell, OnceCell};
+use std::error::Error;
+use std::fmt;
+use std::rc::Rc;
+
+// a lazy, memoized cons list in the spirit of third::List:
+// every cell is shared through Rc, but its contents are computed by a thunk the first time someone looks at it.
+// once forced, the (head, tail) pair is stored in the cell, so every clone of the stream and every later
+// traversal sees the same elements without calling the thunk again
+type Thunk<T> = Box<dyn FnOnce() -> Option<(T, Stream<T>)>>;
+
+pub struct Stream<T> {
+    node: Rc<Node<T>>,
+}
+
+struct Node<T> {
+    // OnceCell gives out references that live as long as the node, so head() can return &T
+    forced: OnceCell<Option<(T, Stream<T>)>>,
+    // the thunk is taken out before it runs, so if it panics (or forces its own cell)
+    // the cell ends up with neither a value nor a thunk, which is the poisoned state
+    thunk: Cell<Option<Thunk<T>>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Poisoned;
+
+impl fmt::Display for Poisoned {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "stream cell poisoned by a panicking thunk")
+    }
+}
+
+impl Error for Poisoned {}
+
+impl<T> Stream<T> {
+    pub fn empty() -> Self {
+        Stream::forced(None)
+    }
+
+    // a stream whose first cell is computed by f when it is first forced
+    pub fn lazy<F>(f: F) -> Self
+    where
+        F: FnOnce() -> Option<(T, Stream<T>)> + 'static,
+    {
+        Stream {
+            node: Rc::new(Node {
+                forced: OnceCell::new(),
+                thunk: Cell::new(Some(Box::new(f))),
+            }),
+        }
+    }
+
+    pub fn cons(val: T, tail: Stream<T>) -> Self {
+        Stream::forced(Some((val, tail)))
+    }
+
+    fn forced(cell: Option<(T, Stream<T>)>) -> Self {
+        let forced = OnceCell::new();
+        let _ = forced.set(cell);
+        Stream {
+            node: Rc::new(Node {
+                forced,
+                thunk: Cell::new(None),
+            }),
+        }
+    }
+
+    // evaluates the first cell if needed, only this cell: the tail stays lazy
+    pub fn force(&self) -> Result<Option<(&T, &Stream<T>)>, Poisoned> {
+        if self.node.forced.get().is_none() {
+            let thunk = self.node.thunk.take().ok_or(Poisoned)?;
+            let _ = self.node.forced.set(thunk());
+        }
+        let cell = self.node.forced.get().ok_or(Poisoned)?;
+        Ok(cell.as_ref().map(|(val, tail)| (val, tail)))
+    }
+
+    pub fn is_forced(&self) -> bool {
+        self.node.forced.get().is_some()
+    }
+
+    pub fn is_poisoned(&self) -> bool {
+        if self.is_forced() {
+            return false;
+        }
+        // Cell<Option<Box<_>>> cannot be peeked at, take the thunk and put it back
+        let thunk = self.node.thunk.take();
+        let poisoned = thunk.is_none();
+        self.node.thunk.set(thunk);
+        poisoned
+    }
+
+    pub fn try_head(&self) -> Result<Option<&T>, Poisoned> {
+        Ok(self.force()?.map(|(val, _)| val))
+    }
+
+    // like third::List::tail(), the tail of an empty stream is empty
+    pub fn try_tail(&self) -> Result<Stream<T>, Poisoned> {
+        Ok(match self.force()? {
+            Some((_, tail)) => tail.clone(),
+            None => Stream::empty(),
+        })
+    }
+
+    pub fn head(&self) -> Option<&T> {
+        self.try_head().unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    pub fn tail(&self) -> Stream<T> {
+        self.try_tail().unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter { next: Some(self) }
+    }
+
+    fn step(&self) -> Option<(&T, &Stream<T>)> {
+        self.force().unwrap_or_else(|err| panic!("{}", err))
+    }
+}
+
+impl<T: 'static> Stream<T> {
+    // pulls elements from f until it returns None, f is only called when a new cell is forced
+    pub fn from_fn<F>(mut f: F) -> Self
+    where
+        F: FnMut() -> Option<T> + 'static,
+    {
+        Stream::lazy(move || f().map(|val| (val, Stream::from_fn(f))))
+    }
+
+    pub fn map<U, F>(&self, f: F) -> Stream<U>
+    where
+        U: 'static,
+        F: Fn(&T) -> U + 'static,
+    {
+        self.map_rc(Rc::new(f))
+    }
+
+    fn map_rc<U: 'static>(&self, f: Rc<dyn Fn(&T) -> U>) -> Stream<U> {
+        let src = self.clone();
+        Stream::lazy(move || {
+            let (val, tail) = src.step()?;
+            Some((f(val), tail.map_rc(f.clone())))
+        })
+    }
+}
+
+impl<T: Clone + 'static> Stream<T> {
+    // seed, f(seed), f(f(seed)), ...
+    pub fn iterate<F>(seed: T, f: F) -> Self
+    where
+        F: Fn(&T) -> T + 'static,
+    {
+        Stream::iterate_rc(seed, Rc::new(f))
+    }
+
+    fn iterate_rc(seed: T, f: Rc<dyn Fn(&T) -> T>) -> Self {
+        Stream::lazy(move || Some(Stream::iterate_cell(seed, f)))
+    }
+
+    // the cell holding seed is already being forced, only the next value is left for later
+    fn iterate_cell(seed: T, f: Rc<dyn Fn(&T) -> T>) -> (T, Stream<T>) {
+        let prev = seed.clone();
+        let tail = Stream::lazy(move || Some(Stream::iterate_cell(f(&prev), f)));
+        (seed, tail)
+    }
+
+    // skipping rejected elements is a loop over the source, not a chain of nested thunks,
+    // so a long run of rejected elements cannot overflow the stack
+    pub fn filter<P>(&self, pred: P) -> Stream<T>
+    where
+        P: Fn(&T) -> bool + 'static,
+    {
+        self.filter_rc(Rc::new(pred))
+    }
+
+    fn filter_rc(&self, pred: Rc<dyn Fn(&T) -> bool>) -> Stream<T> {
+        let src = self.clone();
+        Stream::lazy(move || {
+            let mut cur = src;
+            loop {
+                let next = match cur.step() {
+                    None => return None,
+                    Some((val, tail)) if pred(val) => {
+                        return Some((val.clone(), tail.filter_rc(pred.clone())))
+                    }
+                    Some((_, tail)) => tail.clone(),
+                };
+                cur = next;
+            }
+        })
+    }
+
+    pub fn take(&self, n: usize) -> Stream<T> {
+        let src = self.clone();
+        Stream::lazy(move || {
+            if n == 0 {
+                return None;
+            }
+            let (val, tail) = src.step()?;
+            Some((val.clone(), tail.take(n - 1)))
+        })
+    }
+
+    pub fn zip<U: Clone + 'static>(&self, other: &Stream<U>) -> Stream<(T, U)> {
+        let (left, right) = (self.clone(), other.clone());
+        Stream::lazy(move || {
+            let (a, left) = left.step()?;
+            let (b, right) = right.step()?;
+            Some(((a.clone(), b.clone()), left.zip(right)))
+        })
+    }
+
+    // forces the whole stream, so only use it on finite ones, e.g. after take()
+    pub fn to_list(&self) -> List<T> {
+        let vals: Vec<&T> = self.iter().collect();
+        vals.into_iter()
+            .rev()
+            .fold(List::new(), |mut list, val| list.prepend(val.clone()))
+    }
+}
+
+impl<T> Clone for Stream<T> {
+    fn clone(&self) -> Self {
+        Stream {
+            node: self.node.clone(),
+        }
+    }
+}
+
+// forced cells form an ordinary Rc chain, unlink it in a loop like third::List does
+impl<T> Drop for Node<T> {
+    fn drop(&mut self) {
+        let mut next = match self.forced.take() {
+            Some(Some((_, tail))) => tail,
+            _ => return,
+        };
+        while let Ok(mut node) = Rc::try_unwrap(next.node) {
+            match node.forced.take() {
+                Some(Some((_, tail))) => next = tail,
+                _ => break,
+            }
+        }
+    }
+}
+
+pub struct Iter<'a, T> {
+    next: Option<&'a Stream<T>>,
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+    fn next(&mut self) -> Option<Self::Item> {
+        let (val, tail) = self.next.take()?.step()?;
+        self.next = Some(tail);
+        Some(val)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Poisoned, Stream};
+    use std::cell::Cell;
+    use std::panic::{self, AssertUnwindSafe};
+    use std::rc::Rc;
+
+    fn naturals() -> Stream<u64> {
+        Stream::iterate(0, |n| n + 1)
+    }
+
+    #[test]
+    fn basic_test() {
+        let empty = Stream::<i32>::empty();
+        assert_eq!(empty.head(), None);
+        assert_eq!(empty.tail().head(), None);
+
+        let s = Stream::cons(1, Stream::cons(2, Stream::empty()));
+        assert_eq!(s.head(), Some(&1));
+        assert_eq!(s.tail().head(), Some(&2));
+        assert_eq!(s.iter().collect::<Vec<_>>(), vec![&1, &2]);
+    }
+
+    #[test]
+    fn memoize_test() {
+        let calls = Rc::new(Cell::new(0));
+        let counter = calls.clone();
+        let s = Stream::from_fn(move || {
+            counter.set(counter.get() + 1);
+            Some(counter.get())
+        });
+
+        // nothing is computed until the stream is looked at
+        assert_eq!(calls.get(), 0);
+        assert_eq!(
+            s.take(5).to_list().iter().collect::<Vec<_>>(),
+            vec![&1, &2, &3, &4, &5]
+        );
+        assert_eq!(calls.get(), 5);
+
+        // a clone and a second traversal share the forced cells
+        let copy = s.clone();
+        assert_eq!(copy.iter().take(5).count(), 5);
+        assert_eq!(s.iter().take(3).cloned().collect::<Vec<_>>(), vec![1, 2, 3]);
+        assert_eq!(calls.get(), 5);
+    }
+
+    #[test]
+    fn combinator_test() {
+        let evens = naturals().filter(|n| n % 2 == 0);
+        let squares = evens.map(|n| n * n);
+        let list = squares.take(4).to_list();
+        assert_eq!(list.iter().collect::<Vec<_>>(), vec![&0, &4, &16, &36]);
+
+        let pairs = naturals().zip(&naturals().map(|n| n * 10)).take(3);
+        assert_eq!(
+            pairs.iter().cloned().collect::<Vec<_>>(),
+            vec![(0, 0), (1, 10), (2, 20)]
+        );
+
+        // zip stops at the shorter stream
+        assert_eq!(naturals().take(2).zip(&naturals()).iter().count(), 2);
+        assert_eq!(naturals().take(0).to_list().head(), None);
+    }
+
+    #[test]
+    fn long_stream_test() {
+        // forcing a long prefix walks it in a loop, and dropping it unlinks it in a loop
+        let s = naturals().map(|n| n * 2);
+        assert_eq!(s.iter().nth(500_000), Some(&1_000_000));
+
+        // a filter rejecting almost everything does not nest thunks either
+        let rare = naturals().filter(|n| n % 300_000 == 299_999);
+        assert_eq!(rare.head(), Some(&299_999));
+        drop(s);
+        drop(rare);
+    }
+
+    #[test]
+    fn poison_test() {
+        let mut n = 0;
+        let s = Stream::from_fn(move || {
+            n += 1;
+            if n == 3 {
+                panic!("page 3 failed to load");
+            }
+            Some(n)
+        });
+
+        let result = panic::catch_unwind(AssertUnwindSafe(|| s.iter().count()));
+        assert!(result.is_err());
+
+        // the forced prefix is still readable, the failed cell stays poisoned
+        assert_eq!(s.iter().take(2).collect::<Vec<_>>(), vec![&1, &2]);
+        let broken = s.tail().tail();
+        assert!(broken.is_poisoned());
+        assert_eq!(broken.try_head(), Err(Poisoned));
+        assert!(broken.try_tail().is_err());
+
+        // streams derived from a poisoned one are poisoned as well when they reach it
+        let mapped = s.map(|n| n + 1);
+        let result = panic::catch_unwind(AssertUnwindSafe(|| mapped.iter().count()));
+        assert!(result.is_err());
+        assert!(mapped.tail().tail().is_poisoned());
+        assert!(!s.is_poisoned());
+    }
+}
@@ -0,0 +1,275 @@
+use std::sync::{Arc, Mutex, Weak};
+
+// a deque that several threads can push to and pop from at once, laid out like fourth::List
+// but with Arc<Mutex<Node>> instead of Rc<RefCell<Node>>
+//
+// there is no lock for the whole deque: every node has its own, and an operation only locks the few
+// nodes it relinks. two sentinel nodes sit at the ends, so an operation at the front only ever touches
+// the front sentinel and the first two nodes, and one at the back the last two nodes and the back sentinel.
+// the two ends meet only while the deque holds two elements or fewer.
+//
+// locks are always taken front to back, which rules out deadlock: nodes are only ever inserted or removed,
+// never moved, so any two nodes keep their order for as long as both exist, and no thread can be waiting
+// for a node in front of one it holds. the front end gets its nodes in that order by simply following next
+// under the locks it already holds. the back end cannot: it reads the nodes it needs without holding their
+// locks, locks them front to back, and then checks that they are still neighbours, starting over if another
+// thread got there first
+//
+// as in fourth, only next links own their node and prev is Weak, so the nodes form a plain chain
+pub struct SyncDeque<T> {
+    head: Arc<Mutex<Node<T>>>,
+    tail: Arc<Mutex<Node<T>>>,
+}
+
+type Link<T> = Option<Arc<Mutex<Node<T>>>>;
+
+struct Node<T> {
+    // None only in the sentinels
+    val: Option<T>,
+    next: Link<T>,
+    prev: Weak<Mutex<Node<T>>>,
+}
+
+impl<T> Node<T> {
+    fn new(val: Option<T>) -> Arc<Mutex<Node<T>>> {
+        Arc::new(Mutex::new(Node {
+            val,
+            next: None,
+            prev: Weak::new(),
+        }))
+    }
+}
+
+// whether prev points to node
+fn is<T>(prev: &Weak<Mutex<Node<T>>>, node: &Arc<Mutex<Node<T>>>) -> bool {
+    std::ptr::eq(prev.as_ptr(), Arc::as_ptr(node))
+}
+
+fn points_to<T>(next: &Link<T>, node: &Arc<Mutex<Node<T>>>) -> bool {
+    next.as_ref().is_some_and(|next| Arc::ptr_eq(next, node))
+}
+
+impl<T> SyncDeque<T> {
+    pub fn new() -> Self {
+        let head = Node::new(None);
+        let tail = Node::new(None);
+        head.lock().unwrap().next = Some(tail.clone());
+        tail.lock().unwrap().prev = Arc::downgrade(&head);
+        SyncDeque { head, tail }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        points_to(&self.head.lock().unwrap().next, &self.tail)
+    }
+
+    pub fn push_front(&self, val: T) {
+        let mut head = self.head.lock().unwrap();
+        let first = head.next.clone().unwrap();
+        let mut first_guard = first.lock().unwrap();
+        let node = Node::new(Some(val));
+        {
+            let mut inner = node.lock().unwrap();
+            inner.prev = Arc::downgrade(&self.head);
+            inner.next = Some(first.clone());
+        }
+        first_guard.prev = Arc::downgrade(&node);
+        head.next = Some(node);
+    }
+
+    pub fn pop_front(&self) -> Option<T> {
+        let mut head = self.head.lock().unwrap();
+        let first = head.next.clone().unwrap();
+        if Arc::ptr_eq(&first, &self.tail) {
+            return None;
+        }
+        let mut first_guard = first.lock().unwrap();
+        let second = first_guard.next.take().unwrap();
+        second.lock().unwrap().prev = Arc::downgrade(&self.head);
+        head.next = Some(second);
+        first_guard.val.take()
+    }
+
+    pub fn push_back(&self, val: T) {
+        let node = Node::new(Some(val));
+        loop {
+            // the last node (or the front sentinel) may be gone by the time it is locked
+            let Some(last) = self.tail.lock().unwrap().prev.upgrade() else {
+                continue;
+            };
+            let mut last_guard = last.lock().unwrap();
+            let mut tail = self.tail.lock().unwrap();
+            if !is(&tail.prev, &last) {
+                continue;
+            }
+            {
+                let mut inner = node.lock().unwrap();
+                inner.prev = Arc::downgrade(&last);
+                inner.next = Some(self.tail.clone());
+            }
+            tail.prev = Arc::downgrade(&node);
+            last_guard.next = Some(node);
+            return;
+        }
+    }
+
+    pub fn pop_back(&self) -> Option<T> {
+        loop {
+            let Some(last) = self.tail.lock().unwrap().prev.upgrade() else {
+                continue;
+            };
+            if Arc::ptr_eq(&last, &self.head) {
+                // looks empty, which only the front sentinel can confirm
+                match self.is_empty() {
+                    true => return None,
+                    false => continue,
+                }
+            }
+            let Some(before) = last.lock().unwrap().prev.upgrade() else {
+                continue;
+            };
+            let mut before_guard = before.lock().unwrap();
+            let mut last_guard = last.lock().unwrap();
+            let mut tail = self.tail.lock().unwrap();
+            // a popped node has its next cleared, so this also catches last having been taken from the front
+            if !points_to(&before_guard.next, &last) || !is(&tail.prev, &last) {
+                continue;
+            }
+            last_guard.next = None;
+            tail.prev = Arc::downgrade(&before);
+            before_guard.next = Some(self.tail.clone());
+            return last_guard.val.take();
+        }
+    }
+}
+
+impl<T> Default for SyncDeque<T> {
+    fn default() -> Self {
+        SyncDeque::new()
+    }
+}
+
+// unlinking one node at a time, so a long deque does not drop recursively
+impl<T> Drop for SyncDeque<T> {
+    fn drop(&mut self) {
+        let mut link = self.head.lock().unwrap().next.take();
+        while let Some(node) = link {
+            link = node.lock().unwrap().next.take();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SyncDeque;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::thread;
+
+    #[test]
+    fn basic_test() {
+        let deque = SyncDeque::new();
+        assert!(deque.is_empty());
+        assert_eq!(deque.pop_front(), None);
+        assert_eq!(deque.pop_back(), None);
+
+        deque.push_front(2);
+        deque.push_back(3);
+        deque.push_front(1);
+        deque.push_back(4);
+        assert!(!deque.is_empty());
+        assert_eq!(deque.pop_front(), Some(1));
+        assert_eq!(deque.pop_back(), Some(4));
+        assert_eq!(deque.pop_back(), Some(3));
+        assert_eq!(deque.pop_back(), Some(2));
+        assert_eq!(deque.pop_front(), None);
+
+        // a long deque drops without recursing
+        let long = SyncDeque::new();
+        for i in 0..200_000 {
+            long.push_back(i);
+        }
+        drop(long);
+
+        fn send_sync<T: Send + Sync>() {}
+        send_sync::<SyncDeque<String>>();
+    }
+
+    #[test]
+    fn fifo_test() {
+        // one producer at the back and one consumer at the front see the elements in order
+        let deque = SyncDeque::new();
+        let n = 20_000;
+        thread::scope(|s| {
+            s.spawn(|| {
+                for i in 0..n {
+                    deque.push_back(i);
+                }
+            });
+            let mut expected = 0;
+            while expected < n {
+                match deque.pop_front() {
+                    Some(val) => {
+                        assert_eq!(val, expected);
+                        expected += 1;
+                    }
+                    None => thread::yield_now(),
+                }
+            }
+        });
+        assert!(deque.is_empty());
+    }
+
+    #[test]
+    fn stress_test() {
+        // producers and consumers at both ends: every element comes out exactly once
+        let deque = SyncDeque::new();
+        let producers = 4;
+        let per_producer = 10_000;
+        let total = producers * per_producer;
+        let popped = AtomicUsize::new(0);
+        let mut seen: Vec<usize> = thread::scope(|s| {
+            for p in 0..producers {
+                let deque = &deque;
+                s.spawn(move || {
+                    for i in 0..per_producer {
+                        let val = p * per_producer + i;
+                        match (p + i) % 2 {
+                            0 => deque.push_front(val),
+                            _ => deque.push_back(val),
+                        }
+                    }
+                });
+            }
+            let consumers: Vec<_> = (0..4)
+                .map(|c| {
+                    let (deque, popped) = (&deque, &popped);
+                    s.spawn(move || {
+                        let mut got = Vec::new();
+                        let mut turn = c;
+                        while popped.load(Ordering::SeqCst) < total {
+                            turn += 1;
+                            let val = match turn % 2 {
+                                0 => deque.pop_front(),
+                                _ => deque.pop_back(),
+                            };
+                            match val {
+                                Some(val) => {
+                                    got.push(val);
+                                    popped.fetch_add(1, Ordering::SeqCst);
+                                }
+                                None => thread::yield_now(),
+                            }
+                        }
+                        got
+                    })
+                })
+                .collect();
+            consumers
+                .into_iter()
+                .flat_map(|consumer| consumer.join().unwrap())
+                .collect()
+        });
+        seen.sort();
+        assert_eq!(seen, (0..total).collect::<Vec<_>>());
+        assert!(deque.is_empty());
+    }
+}
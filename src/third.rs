@@ -1,14 +1,18 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{Hash, Hasher};
 use std::rc::Rc;
 
-type Link<T> = Option<Rc<Node<T>>>;
+pub(crate) type Link<T> = Option<Rc<Node<T>>>;
 
-struct Node<T> {
-    val: T,
-    next: Link<T>,
+// nodes stay private to the crate, sibling modules need them to tell shared nodes apart by address
+pub(crate) struct Node<T> {
+    pub(crate) val: T,
+    pub(crate) next: Link<T>,
 }
 
-struct List<T> {
-    head: Link<T>,
+pub struct List<T> {
+    pub(crate) head: Link<T>,
 }
 
 impl<T> List<T> {
@@ -42,6 +46,298 @@ impl<T> List<T> {
     }
 }
 
+impl<T> Default for List<T> {
+    fn default() -> Self {
+        List::new()
+    }
+}
+
+// cloning a persistent list is cheap: only the Rc of head is cloned, every node is shared
+impl<T> Clone for List<T> {
+    fn clone(&self) -> Self {
+        List {
+            head: self.head.clone(),
+        }
+    }
+}
+
+// structural-sharing diagnostics
+// two versions of a list built by prepend() and tail() share every node after the point they diverged,
+// and the only way to tell is comparing the Rc pointers, not the values
+impl<T> List<T> {
+    // true if both lists start with the very same node (or both are empty),
+    // which means they are the same version and hold exactly the same elements
+    pub fn ptr_eq(&self, other: &List<T>) -> bool {
+        match (&self.head, &other.head) {
+            (Some(a), Some(b)) => Rc::ptr_eq(a, b),
+            (None, None) => true,
+            _ => false,
+        }
+    }
+
+    // number of nodes a and b physically share, that is the length of their common tail
+    pub fn shared_suffix_len(a: &List<T>, b: &List<T>) -> usize {
+        List::common_link(a, b).map_or(0, |node| Self::link_len(Some(node)))
+    }
+
+    // the longest tail shared by a and b, which is where the two versions diverged
+    // the returned list shares those nodes instead of copying them
+    pub fn common_tail(a: &List<T>, b: &List<T>) -> List<T> {
+        List {
+            head: List::common_link(a, b).cloned(),
+        }
+    }
+
+    // number of nodes owned by this list alone, which is exactly what Drop would free
+    // Drop stops at the first node whose reference count is not 1, so do we
+    pub fn unique_prefix_len(&self) -> usize {
+        let mut count = 0;
+        let mut cur = self.head.as_ref();
+        while let Some(node) = cur {
+            if Rc::strong_count(node) != 1 {
+                break;
+            }
+            count += 1;
+            cur = node.next.as_ref();
+        }
+        count
+    }
+
+    fn link_len(mut link: Option<&Rc<Node<T>>>) -> usize {
+        let mut len = 0;
+        while let Some(node) = link {
+            len += 1;
+            link = node.next.as_ref();
+        }
+        len
+    }
+
+    // shared nodes sit at the same distance from the end of both lists,
+    // so skip the extra nodes of the longer one and walk both in lockstep until the pointers meet
+    fn common_link<'a>(a: &'a List<T>, b: &'a List<T>) -> Option<&'a Rc<Node<T>>> {
+        let mut a_len = Self::link_len(a.head.as_ref());
+        let mut b_len = Self::link_len(b.head.as_ref());
+        let mut a = a.head.as_ref();
+        let mut b = b.head.as_ref();
+        while a_len > b_len {
+            a = a.and_then(|node| node.next.as_ref());
+            a_len -= 1;
+        }
+        while b_len > a_len {
+            b = b.and_then(|node| node.next.as_ref());
+            b_len -= 1;
+        }
+        while let (Some(x), Some(y)) = (a, b) {
+            if Rc::ptr_eq(x, y) {
+                return Some(x);
+            }
+            a = x.next.as_ref();
+            b = y.next.as_ref();
+        }
+        None
+    }
+}
+
+// sorted-set algebra
+// the lists are expected to be sorted in ascending order from head to tail, and for the set operations
+// to hold every element once. a result is a new version like prepend() makes: its front is freshly built,
+// but its longest tail that already exists as a run of input nodes is shared instead of copied
+impl<T: Ord> List<T> {
+    pub fn is_sorted(&self) -> bool {
+        let mut iter = self.iter();
+        let mut prev = match iter.next() {
+            Some(val) => val,
+            None => return true,
+        };
+        for val in iter {
+            if prev > val {
+                return false;
+            }
+            prev = val;
+        }
+        true
+    }
+}
+
+// which elements a merge walk keeps
+struct Keep {
+    // elements only in self, only in other, and in both
+    a: bool,
+    b: bool,
+    both: bool,
+    // keep an element of both twice, as merge() does, instead of once
+    twice: bool,
+}
+
+impl<T: Ord + Clone> List<T> {
+    // an element already in the list leaves it as it is, the very same version is returned
+    pub fn insert_sorted(&self, val: T) -> List<T> {
+        let mut picks = Vec::new();
+        let mut link = self.head.as_ref();
+        while let Some(node) = link {
+            match node.val.cmp(&val) {
+                Ordering::Less => picks.push([Some(node), None]),
+                Ordering::Equal => return self.clone(),
+                Ordering::Greater => break,
+            }
+            link = node.next.as_ref();
+        }
+        let rest = Rc::new(Node {
+            val,
+            next: link.cloned(),
+        });
+        List::build(picks, Some(rest))
+    }
+
+    // all elements of both lists, duplicates kept, those of self first among equal ones
+    pub fn merge(&self, other: &List<T>) -> List<T> {
+        self.combine(
+            other,
+            Keep {
+                a: true,
+                b: true,
+                both: true,
+                twice: true,
+            },
+        )
+    }
+
+    pub fn union(&self, other: &List<T>) -> List<T> {
+        self.combine(
+            other,
+            Keep {
+                a: true,
+                b: true,
+                both: true,
+                twice: false,
+            },
+        )
+    }
+
+    pub fn intersection(&self, other: &List<T>) -> List<T> {
+        self.combine(
+            other,
+            Keep {
+                a: false,
+                b: false,
+                both: true,
+                twice: false,
+            },
+        )
+    }
+
+    // elements of self that are not in other
+    pub fn difference(&self, other: &List<T>) -> List<T> {
+        self.combine(
+            other,
+            Keep {
+                a: true,
+                b: false,
+                both: false,
+                twice: false,
+            },
+        )
+    }
+
+    pub fn symmetric_difference(&self, other: &List<T>) -> List<T> {
+        self.combine(
+            other,
+            Keep {
+                a: true,
+                b: true,
+                both: false,
+                twice: false,
+            },
+        )
+    }
+
+    // walks both lists like a merge sort merge, collecting the input nodes of the elements kept.
+    // once one side runs out (or both reach the same node, where everything left is in both)
+    // the result's tail is simply an input link
+    fn combine(&self, other: &List<T>, keep: Keep) -> List<T> {
+        let mut picks = Vec::new();
+        let mut a = self.head.as_ref();
+        let mut b = other.head.as_ref();
+        let rest = loop {
+            match (a, b) {
+                (Some(x), Some(y)) if !keep.twice && Rc::ptr_eq(x, y) => {
+                    break if keep.both { Some(x) } else { None };
+                }
+                (Some(x), Some(y)) => match x.val.cmp(&y.val) {
+                    Ordering::Less => {
+                        if keep.a {
+                            picks.push([Some(x), None]);
+                        }
+                        a = x.next.as_ref();
+                    }
+                    Ordering::Greater => {
+                        if keep.b {
+                            picks.push([Some(y), None]);
+                        }
+                        b = y.next.as_ref();
+                    }
+                    // merge() takes the one from self now and meets the one from other again next round
+                    Ordering::Equal if keep.twice => {
+                        picks.push([Some(x), None]);
+                        a = x.next.as_ref();
+                    }
+                    Ordering::Equal => {
+                        if keep.both {
+                            picks.push([Some(x), Some(y)]);
+                        }
+                        a = x.next.as_ref();
+                        b = y.next.as_ref();
+                    }
+                },
+                (Some(x), None) => break if keep.a { Some(x) } else { None },
+                (None, Some(y)) => break if keep.b { Some(y) } else { None },
+                (None, None) => break None,
+            }
+        };
+        List::build(picks, rest.cloned())
+    }
+
+    // builds picks followed by rest, where every pick is an element given by the input nodes holding it.
+    // going backwards from rest, as long as some node of a pick already points at what is built so far,
+    // that node is the list built so far: a whole run of input nodes is reused without looking at its values.
+    // only the picks in front of the longest such run are copied into new nodes
+    fn build(picks: Vec<[Option<&Rc<Node<T>>>; 2]>, rest: Link<T>) -> List<T> {
+        let links_eq = |a: &Link<T>, b: Option<&Rc<Node<T>>>| match (a, b) {
+            (Some(a), Some(b)) => Rc::ptr_eq(a, b),
+            (None, None) => true,
+            _ => false,
+        };
+
+        let mut head = rest;
+        let mut candidates = vec![head.as_ref()];
+        let mut copied = picks.len();
+        while copied > 0 {
+            let found: Vec<_> = picks[copied - 1]
+                .iter()
+                .flatten()
+                .filter(|node| candidates.iter().any(|c| links_eq(&node.next, *c)))
+                .map(|node| Some(*node))
+                .collect();
+            if found.is_empty() {
+                break;
+            }
+            candidates = found;
+            copied -= 1;
+        }
+        if copied < picks.len() {
+            head = candidates[0].cloned();
+        }
+
+        for pick in picks[..copied].iter().rev() {
+            head = Some(Rc::new(Node {
+                val: pick[0].unwrap().val.clone(),
+                next: head,
+            }));
+        }
+        List { head }
+    }
+}
+
 impl<T> List<T> {
     pub fn iter(&self) -> Iter<T> {
         Iter {
@@ -64,6 +360,95 @@ impl<'a, T> Iterator for Iter<'a, T> {
     }
 }
 
+// Debug, comparison and hashing
+// all of them walk the lists in a loop, and comparisons stop as soon as both sides reach the same node:
+// from there on the two lists are the very same nodes, so nothing after it can differ
+impl<T: fmt::Debug> fmt::Debug for List<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+// true once both lists are at the same node (or both ended), everything from there on is identical
+fn same_link<T>(a: &Link<T>, b: &Link<T>) -> bool {
+    match (a, b) {
+        (Some(x), Some(y)) => Rc::ptr_eq(x, y),
+        (None, None) => true,
+        _ => false,
+    }
+}
+
+// note that for T like f64 a shared NaN is equal to itself here, the nodes are not even looked at
+impl<T: PartialEq> PartialEq for List<T> {
+    fn eq(&self, other: &List<T>) -> bool {
+        let (mut a, mut b) = (&self.head, &other.head);
+        while !same_link(a, b) {
+            match (a.as_deref(), b.as_deref()) {
+                (Some(x), Some(y)) if x.val == y.val => {
+                    a = &x.next;
+                    b = &y.next;
+                }
+                _ => return false,
+            }
+        }
+        true
+    }
+}
+
+impl<T: Eq> Eq for List<T> {}
+
+// lexicographic, like slices: a list that is a prefix of the other is smaller
+impl<T: PartialOrd> PartialOrd for List<T> {
+    fn partial_cmp(&self, other: &List<T>) -> Option<Ordering> {
+        let (mut a, mut b) = (&self.head, &other.head);
+        while !same_link(a, b) {
+            match (a.as_deref(), b.as_deref()) {
+                (Some(x), Some(y)) => match x.val.partial_cmp(&y.val) {
+                    Some(Ordering::Equal) => {
+                        a = &x.next;
+                        b = &y.next;
+                    }
+                    ordering => return ordering,
+                },
+                (Some(_), None) => return Some(Ordering::Greater),
+                (None, _) => return Some(Ordering::Less),
+            }
+        }
+        Some(Ordering::Equal)
+    }
+}
+
+impl<T: Ord> Ord for List<T> {
+    fn cmp(&self, other: &List<T>) -> Ordering {
+        let (mut a, mut b) = (&self.head, &other.head);
+        while !same_link(a, b) {
+            match (a.as_deref(), b.as_deref()) {
+                (Some(x), Some(y)) => match x.val.cmp(&y.val) {
+                    Ordering::Equal => {
+                        a = &x.next;
+                        b = &y.next;
+                    }
+                    ordering => return ordering,
+                },
+                (Some(_), None) => return Ordering::Greater,
+                (None, _) => return Ordering::Less,
+            }
+        }
+        Ordering::Equal
+    }
+}
+
+// the length first, as slices do, so lists that are prefixes of each other do not feed the same sequence
+// equal lists hash alike whether they share nodes or not, since only values are hashed
+impl<T: Hash> Hash for List<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_usize(List::link_len(self.head.as_ref()));
+        for val in self.iter() {
+            val.hash(state);
+        }
+    }
+}
+
 impl<T> Drop for List<T> {
     fn drop(&mut self) {
         // head: Option<Rc<Node<T>>>
@@ -85,6 +470,10 @@ impl<T> Drop for List<T> {
 mod tests {
 
     use super::List;
+    use std::cmp::Ordering;
+    use std::collections::hash_map::DefaultHasher;
+    use std::collections::HashSet;
+    use std::hash::{Hash, Hasher};
     use std::rc::Rc;
 
     #[test]
@@ -155,4 +544,219 @@ mod tests {
         assert_eq!(iter.next(), Some(&2));
         assert_eq!(iter.next(), Some(&1));
     }
+
+    #[test]
+    fn sharing_test() {
+        // 3 -> 2 -> 1
+        let mut list = List::new().prepend(1).prepend(2).prepend(3);
+        assert!(list.ptr_eq(&list.clone()));
+        assert!(List::<i32>::new().ptr_eq(&List::new()));
+
+        //      3
+        //      |
+        //      v
+        // 4 -> 2 -> 1
+        let branch = list.tail().prepend(4);
+        assert!(!list.ptr_eq(&branch));
+        assert_eq!(List::shared_suffix_len(&list, &branch), 2);
+
+        let base = List::common_tail(&list, &branch);
+        assert!(base.ptr_eq(&list.tail()));
+        assert_eq!(base.iter().collect::<Vec<_>>(), vec![&2, &1]);
+
+        // equal values are not shared nodes
+        let copy = List::new().prepend(1).prepend(2).prepend(3);
+        assert_eq!(List::shared_suffix_len(&list, &copy), 0);
+        assert_eq!(List::common_tail(&list, &copy).head(), None);
+
+        // a suffix of a list shares itself entirely
+        let one = list.tail().tail();
+        assert_eq!(List::shared_suffix_len(&list, &one), 1);
+        assert!(List::common_tail(&one, &list).ptr_eq(&one));
+    }
+
+    #[test]
+    fn unique_prefix_test() {
+        let mut list = List::new().prepend(1).prepend(2).prepend(3);
+        assert_eq!(list.unique_prefix_len(), 3);
+
+        // 2 -> 1 is now also owned by branch, only 3 would be freed
+        let branch = list.tail().prepend(4);
+        assert_eq!(list.unique_prefix_len(), 1);
+        assert_eq!(branch.unique_prefix_len(), 1);
+
+        drop(branch);
+        assert_eq!(list.unique_prefix_len(), 3);
+
+        // a clone shares the head itself, dropping either frees nothing
+        let copy = list.clone();
+        assert_eq!(copy.unique_prefix_len(), 0);
+        assert_eq!(List::<i32>::new().unique_prefix_len(), 0);
+    }
+
+    fn sorted(vals: &[i32]) -> List<i32> {
+        vals.iter()
+            .rev()
+            .fold(List::new(), |mut list, &val| list.prepend(val))
+    }
+
+    fn values(list: &List<i32>) -> Vec<i32> {
+        list.iter().cloned().collect()
+    }
+
+    #[test]
+    fn set_algebra_test() {
+        let a = sorted(&[1, 3, 5, 7, 9]);
+        let b = sorted(&[2, 3, 4, 9, 10, 11]);
+        assert!(a.is_sorted() && b.is_sorted());
+        assert!(!sorted(&[2, 1]).is_sorted());
+
+        assert_eq!(values(&a.union(&b)), vec![1, 2, 3, 4, 5, 7, 9, 10, 11]);
+        assert_eq!(values(&a.intersection(&b)), vec![3, 9]);
+        assert_eq!(values(&a.difference(&b)), vec![1, 5, 7]);
+        assert_eq!(values(&b.difference(&a)), vec![2, 4, 10, 11]);
+        assert_eq!(
+            values(&a.symmetric_difference(&b)),
+            vec![1, 2, 4, 5, 7, 10, 11]
+        );
+        assert_eq!(
+            values(&a.merge(&b)),
+            vec![1, 2, 3, 3, 4, 5, 7, 9, 9, 10, 11]
+        );
+        assert!(a.merge(&b).is_sorted());
+
+        // a pseudo-random cross-check against BTreeSet
+        let mut seed = 12345u64;
+        let mut random_set = || {
+            let mut set = std::collections::BTreeSet::new();
+            for _ in 0..40 {
+                seed = seed
+                    .wrapping_mul(6364136223846793005)
+                    .wrapping_add(1442695040888963407);
+                set.insert((seed >> 59) as i32);
+            }
+            set
+        };
+        for _ in 0..50 {
+            let (x, y) = (random_set(), random_set());
+            let vx: Vec<i32> = x.iter().cloned().collect();
+            let vy: Vec<i32> = y.iter().cloned().collect();
+            let (lx, ly) = (sorted(&vx), sorted(&vy));
+            assert_eq!(
+                values(&lx.union(&ly)),
+                x.union(&y).cloned().collect::<Vec<_>>()
+            );
+            assert_eq!(
+                values(&lx.intersection(&ly)),
+                x.intersection(&y).cloned().collect::<Vec<_>>()
+            );
+            assert_eq!(
+                values(&lx.difference(&ly)),
+                x.difference(&y).cloned().collect::<Vec<_>>()
+            );
+            assert_eq!(
+                values(&lx.symmetric_difference(&ly)),
+                x.symmetric_difference(&y).cloned().collect::<Vec<_>>()
+            );
+        }
+    }
+
+    #[test]
+    fn set_sharing_test() {
+        // 1 -> 5 -> 8 -> 9 and 2 -> 5 -> 8 -> 9 share 5 -> 8 -> 9
+        let mut shared = sorted(&[5, 8, 9]);
+        let a = shared.prepend(1);
+        let b = shared.prepend(2);
+
+        // only the part in front of the shared nodes is built
+        let mut union = a.union(&b);
+        assert_eq!(values(&union), vec![1, 2, 5, 8, 9]);
+        assert!(union.tail().tail().ptr_eq(&shared));
+        assert!(a.intersection(&b).ptr_eq(&shared));
+        assert_eq!(a.difference(&b).head(), Some(&1));
+        assert_eq!(a.difference(&b).iter().count(), 1);
+
+        // once one side runs out, the rest of the other is reused
+        let small = sorted(&[0, 1]);
+        let big = sorted(&[3, 4, 5, 6]);
+        assert!(small.union(&big).tail().tail().ptr_eq(&big));
+        assert!(big.difference(&small).ptr_eq(&big));
+
+        // a tail made of equal elements is taken from one input as a whole run
+        let mut c = sorted(&[1, 2, 3, 7, 8]);
+        let mut d = sorted(&[4, 7, 8]);
+        let both = c.intersection(&d);
+        assert!(both.ptr_eq(&c.tail().tail().tail()) || both.ptr_eq(&d.tail()));
+        assert_eq!(both.unique_prefix_len(), 0);
+        assert!(a.union(&a).ptr_eq(&a));
+        assert!(a.symmetric_difference(&a).head().is_none());
+
+        // insert copies the nodes in front of the new one and shares the rest
+        let mut list = sorted(&[1, 2, 4, 5]);
+        let mut inserted = list.insert_sorted(3);
+        assert_eq!(values(&inserted), vec![1, 2, 3, 4, 5]);
+        assert!(inserted.tail().tail().tail().ptr_eq(&list.tail().tail()));
+        assert!(list.insert_sorted(4).ptr_eq(&list));
+        assert!(List::new().insert_sorted(1).is_sorted());
+    }
+
+    #[test]
+    fn eq_ord_test() {
+        let mut list = sorted(&[1, 2, 3]);
+        assert_eq!(format!("{:?}", list), "[1, 2, 3]");
+        assert_eq!(format!("{:?}", List::<i32>::new()), "[]");
+
+        // equal values are equal lists, shared or not
+        assert_eq!(list, sorted(&[1, 2, 3]));
+        assert_eq!(list, list.clone());
+        assert_ne!(list, sorted(&[1, 2]));
+        assert_ne!(list, list.clone().tail().prepend(5));
+        assert_eq!(List::<i32>::new(), List::new());
+
+        assert!(sorted(&[1, 2]) < list);
+        assert!(list < sorted(&[1, 3]));
+        assert!(list.tail().prepend(0) < list);
+        assert_eq!(list.cmp(&list.clone()), Ordering::Equal);
+        assert_eq!(
+            list.prepend(4).partial_cmp(&list.prepend(4)),
+            Some(Ordering::Equal)
+        );
+
+        // a shared NaN compares equal without being looked at, a copied one does not
+        let nan = List::new().prepend(f64::NAN);
+        assert_eq!(nan, nan.clone());
+        assert_ne!(nan, List::new().prepend(f64::NAN));
+    }
+
+    #[test]
+    fn hash_test() {
+        let hash = |list: &List<i32>| {
+            let mut hasher = DefaultHasher::new();
+            list.hash(&mut hasher);
+            hasher.finish()
+        };
+        let mut list = sorted(&[1, 2, 3]);
+        assert_eq!(hash(&list), hash(&sorted(&[1, 2, 3])));
+        assert_ne!(hash(&list), hash(&list.tail()));
+
+        let mut set = HashSet::new();
+        set.insert(list.clone());
+        assert!(set.contains(&sorted(&[1, 2, 3])));
+        assert!(!set.contains(&sorted(&[1, 2])));
+    }
+
+    #[test]
+    fn long_compare_test() {
+        // a million elements: loops, not recursion, and the shared tail is skipped
+        let mut shared = (0..1_000_000).fold(List::new(), |mut list, i| list.prepend(i));
+        let mut a = shared.prepend(-1);
+        let mut b = shared.prepend(-1);
+        assert_eq!(a, b);
+        assert!(a.prepend(0) < b.prepend(1));
+
+        let mut copy = (0..1_000_000).fold(List::new(), |mut list, i| list.prepend(i));
+        assert_eq!(copy, shared);
+        assert!(copy.prepend(0) < shared);
+        assert_eq!(format!("{:?}", shared).len(), format!("{:?}", copy).len());
+    }
 }